@@ -0,0 +1,91 @@
+use bytes::Bytes;
+use primitive_types::{H256, U256};
+use std::collections::HashMap;
+
+use crate::keccak;
+
+/// Keccak-256 of empty input, the code hash of every account without code.
+pub const KECCAK_EMPTY: H256 = H256([
+    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+    0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+]);
+
+/// An account in the world state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Account {
+    pub nonce: u64,
+    pub balance: U256,
+    pub code: Bytes,
+    /// Keccak-256 of `code`, kept in sync by `with_code`.
+    pub code_hash: H256,
+    pub storage: HashMap<U256, U256>,
+}
+
+impl Default for Account {
+    fn default() -> Self {
+        Self {
+            nonce: 0,
+            balance: U256::zero(),
+            code: Bytes::new(),
+            code_hash: KECCAK_EMPTY,
+            storage: HashMap::new(),
+        }
+    }
+}
+
+impl Account {
+    pub fn new(balance: U256) -> Self {
+        Self {
+            balance,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_nonce(mut self, nonce: u64) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    /// Sets the code along with its hash.
+    pub fn with_code(mut self, code: Bytes) -> Self {
+        self.set_code(code);
+        self
+    }
+
+    pub fn set_code(&mut self, code: Bytes) {
+        self.code_hash = H256(keccak::keccak256(&code));
+        self.code = code;
+    }
+
+    pub fn with_storage(mut self, key: U256, value: U256) -> Self {
+        self.storage.insert(key, value);
+        self
+    }
+
+    /// Empty as defined by EIP-161: no nonce, no balance and no code.
+    pub fn is_empty(&self) -> bool {
+        self.nonce == 0 && self.balance.is_zero() && self.code_hash == KECCAK_EMPTY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_code_hash() {
+        assert_eq!(KECCAK_EMPTY, H256(keccak::keccak256(&[])));
+        assert!(Account::default().is_empty());
+    }
+
+    #[test]
+    fn with_code_updates_hash() {
+        let account = Account::new(U256::zero()).with_code(Bytes::from_static(&[0x00]));
+        // keccak256(0x00)
+        assert_eq!(
+            hex::encode(account.code_hash),
+            "bc36789e7a1e281436464229828f817d6612f7b477d66591ff96a9e064bcc98a"
+        );
+        assert!(!account.is_empty());
+    }
+}
@@ -0,0 +1,298 @@
+//! Two's-complement, modular and shift helpers for the EVM arithmetic, comparison and
+//! bitwise opcodes. Division follows the Yellow Paper: a zero divisor or modulus yields zero.
+use primitive_types::{U256, U512};
+
+/// Smallest signed word, -2^255.
+pub const MIN_NEGATIVE: U256 = U256([0, 0, 0, 0x8000_0000_0000_0000]);
+
+pub fn is_negative(value: U256) -> bool {
+    value.bit(255)
+}
+
+/// Two's-complement negation.
+pub fn negate(value: U256) -> U256 {
+    (!value).overflowing_add(U256::one()).0
+}
+
+/// Absolute value of a signed word. `MIN_NEGATIVE` maps onto itself.
+pub fn abs(value: U256) -> U256 {
+    if is_negative(value) {
+        negate(value)
+    } else {
+        value
+    }
+}
+
+pub fn div(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return U256::zero();
+    }
+    a / b
+}
+
+pub fn sdiv(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return U256::zero();
+    }
+    // -2^255 / -1 overflows back to -2^255, which the unsigned division below yields as well
+    let quotient = abs(a) / abs(b);
+    if is_negative(a) != is_negative(b) {
+        negate(quotient)
+    } else {
+        quotient
+    }
+}
+
+pub fn rem(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return U256::zero();
+    }
+    a % b
+}
+
+/// Signed modulo; the result takes the sign of the dividend.
+pub fn smod(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return U256::zero();
+    }
+    let remainder = abs(a) % abs(b);
+    if is_negative(a) {
+        negate(remainder)
+    } else {
+        remainder
+    }
+}
+
+/// (a + b) % n, computed without overflowing 256 bits.
+pub fn addmod(a: U256, b: U256, n: U256) -> U256 {
+    if n.is_zero() {
+        return U256::zero();
+    }
+    let sum = U512::from(a) + U512::from(b);
+    narrow(sum % U512::from(n))
+}
+
+/// (a * b) % n, computed without overflowing 256 bits.
+pub fn mulmod(a: U256, b: U256, n: U256) -> U256 {
+    if n.is_zero() {
+        return U256::zero();
+    }
+    narrow(a.full_mul(b) % U512::from(n))
+}
+
+pub fn exp(base: U256, exponent: U256) -> U256 {
+    base.overflowing_pow(exponent).0
+}
+
+/// Extends the sign bit of the (byte_index + 1)-byte value `value` to a full word.
+pub fn signextend(byte_index: U256, value: U256) -> U256 {
+    if byte_index >= U256::from(31) {
+        return value;
+    }
+    let sign_bit = byte_index.as_usize() * 8 + 7;
+    let mask = (U256::one() << sign_bit) - 1;
+    if value.bit(sign_bit) {
+        value | !mask
+    } else {
+        value & mask
+    }
+}
+
+/// Signed less-than.
+pub fn slt(a: U256, b: U256) -> bool {
+    match (is_negative(a), is_negative(b)) {
+        (true, false) => true,
+        (false, true) => false,
+        _ => a < b,
+    }
+}
+
+/// Signed greater-than.
+pub fn sgt(a: U256, b: U256) -> bool {
+    slt(b, a)
+}
+
+/// The `index`th byte of `value`, counting from the most significant one.
+pub fn byte(index: U256, value: U256) -> U256 {
+    if index >= U256::from(32) {
+        return U256::zero();
+    }
+    value.byte(31 - index.as_usize()).into()
+}
+
+/// Logical shift left (EIP-145). Shifting by 256 or more yields zero.
+pub fn shl(shift: U256, value: U256) -> U256 {
+    if shift >= U256::from(256) {
+        return U256::zero();
+    }
+    value << shift.as_usize()
+}
+
+/// Logical shift right (EIP-145). Shifting by 256 or more yields zero.
+pub fn shr(shift: U256, value: U256) -> U256 {
+    if shift >= U256::from(256) {
+        return U256::zero();
+    }
+    value >> shift.as_usize()
+}
+
+/// Arithmetic shift right (EIP-145). Shifting by 256 or more yields 0 or -1 depending on sign.
+pub fn sar(shift: U256, value: U256) -> U256 {
+    if !is_negative(value) {
+        return shr(shift, value);
+    }
+    if shift >= U256::from(256) {
+        return U256::MAX;
+    }
+    let shift = shift.as_usize();
+    // Fill the vacated high bits with ones
+    (value >> shift) | !(U256::MAX >> shift)
+}
+
+/// Narrows a value known to be smaller than 2^256.
+fn narrow(value: U512) -> U256 {
+    U256::try_from(value).expect("value reduced modulo a 256-bit number")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn neg(value: u64) -> U256 {
+        negate(value.into())
+    }
+
+    #[test]
+    fn test_negate() {
+        assert_eq!(negate(U256::one()), U256::MAX);
+        assert_eq!(negate(U256::zero()), U256::zero());
+        assert_eq!(negate(MIN_NEGATIVE), MIN_NEGATIVE);
+        assert!(is_negative(neg(5)));
+        assert_eq!(abs(neg(5)), 5.into());
+    }
+
+    #[test]
+    fn test_div_and_mod() {
+        assert_eq!(div(10.into(), 3.into()), 3.into());
+        assert_eq!(div(10.into(), U256::zero()), U256::zero());
+        assert_eq!(rem(10.into(), 3.into()), 1.into());
+        assert_eq!(rem(10.into(), U256::zero()), U256::zero());
+    }
+
+    #[test]
+    fn test_sdiv() {
+        assert_eq!(sdiv(10.into(), 10.into()), 1.into());
+        assert_eq!(sdiv(neg(2), neg(1)), 2.into());
+        assert_eq!(sdiv(neg(10), 3.into()), neg(3));
+        assert_eq!(sdiv(10.into(), neg(3)), neg(3));
+        assert_eq!(sdiv(neg(1), U256::zero()), U256::zero());
+        assert_eq!(sdiv(MIN_NEGATIVE, neg(1)), MIN_NEGATIVE);
+    }
+
+    #[test]
+    fn test_smod() {
+        assert_eq!(smod(10.into(), 3.into()), 1.into());
+        assert_eq!(smod(neg(10), 3.into()), neg(1));
+        assert_eq!(smod(neg(8), neg(3)), neg(2));
+        assert_eq!(smod(10.into(), neg(3)), 1.into());
+        assert_eq!(smod(neg(10), U256::zero()), U256::zero());
+        assert_eq!(smod(MIN_NEGATIVE, neg(1)), U256::zero());
+    }
+
+    #[test]
+    fn test_addmod() {
+        assert_eq!(addmod(10.into(), 10.into(), 8.into()), 4.into());
+        assert_eq!(addmod(U256::MAX, 2.into(), 2.into()), 1.into());
+        assert_eq!(addmod(U256::MAX, U256::MAX, U256::MAX), U256::zero());
+        assert_eq!(addmod(1.into(), 2.into(), U256::zero()), U256::zero());
+    }
+
+    #[test]
+    fn test_mulmod() {
+        assert_eq!(mulmod(10.into(), 10.into(), 8.into()), 4.into());
+        assert_eq!(mulmod(U256::MAX, U256::MAX, 12.into()), 9.into());
+        assert_eq!(mulmod(U256::MAX, U256::MAX, U256::MAX - 1), U256::one());
+        assert_eq!(mulmod(2.into(), 3.into(), U256::zero()), U256::zero());
+    }
+
+    #[test]
+    fn test_exp() {
+        assert_eq!(exp(10.into(), 2.into()), 100.into());
+        assert_eq!(exp(2.into(), 255.into()), MIN_NEGATIVE);
+        assert_eq!(exp(2.into(), 256.into()), U256::zero());
+        assert_eq!(exp(U256::zero(), U256::zero()), U256::one());
+        assert_eq!(exp(neg(1), 3.into()), U256::MAX);
+    }
+
+    #[test]
+    fn test_signextend() {
+        assert_eq!(signextend(U256::zero(), 0xff.into()), U256::MAX);
+        assert_eq!(signextend(U256::zero(), 0x7f.into()), 0x7f.into());
+        assert_eq!(signextend(U256::one(), 0xff80.into()), neg(0x80));
+        assert_eq!(signextend(U256::zero(), 0x1ff.into()), U256::MAX);
+        assert_eq!(signextend(U256::zero(), 0x17f.into()), 0x7f.into());
+        assert_eq!(signextend(31.into(), 0xff.into()), 0xff.into());
+        assert_eq!(signextend(U256::MAX, 0xff.into()), 0xff.into());
+    }
+
+    #[test]
+    fn test_signed_comparison() {
+        assert!(slt(neg(1), U256::zero()));
+        assert!(!slt(U256::zero(), neg(1)));
+        assert!(slt(neg(2), neg(1)));
+        assert!(slt(1.into(), 2.into()));
+        assert!(!slt(1.into(), 1.into()));
+        assert!(slt(MIN_NEGATIVE, MIN_NEGATIVE - 1));
+        assert!(sgt(U256::zero(), neg(1)));
+        assert!(sgt(neg(1), neg(2)));
+        assert!(!sgt(neg(1), neg(1)));
+    }
+
+    #[test]
+    fn test_byte() {
+        let value = U256::from_big_endian(&(1..=32).collect::<Vec<u8>>());
+        assert_eq!(byte(U256::zero(), value), 1.into());
+        assert_eq!(byte(31.into(), value), 32.into());
+        assert_eq!(byte(32.into(), value), U256::zero());
+        assert_eq!(byte(U256::MAX, value), U256::zero());
+    }
+
+    // Test vectors from EIP-145
+    #[test]
+    fn test_shl() {
+        assert_eq!(shl(U256::zero(), 1.into()), 1.into());
+        assert_eq!(shl(1.into(), 1.into()), 2.into());
+        assert_eq!(shl(255.into(), 1.into()), MIN_NEGATIVE);
+        assert_eq!(shl(256.into(), 1.into()), U256::zero());
+        assert_eq!(shl(U256::one(), U256::MAX), U256::MAX - 1);
+        assert_eq!(shl(U256::MAX, U256::MAX), U256::zero());
+    }
+
+    #[test]
+    fn test_shr() {
+        assert_eq!(shr(U256::zero(), 1.into()), 1.into());
+        assert_eq!(shr(1.into(), 1.into()), U256::zero());
+        assert_eq!(shr(1.into(), MIN_NEGATIVE), U256::one() << 254);
+        assert_eq!(shr(255.into(), MIN_NEGATIVE), 1.into());
+        assert_eq!(shr(256.into(), MIN_NEGATIVE), U256::zero());
+        assert_eq!(shr(U256::MAX, U256::MAX), U256::zero());
+    }
+
+    #[test]
+    fn test_sar() {
+        assert_eq!(sar(U256::zero(), 1.into()), 1.into());
+        assert_eq!(sar(1.into(), 1.into()), U256::zero());
+        assert_eq!(
+            sar(1.into(), MIN_NEGATIVE),
+            MIN_NEGATIVE | (U256::one() << 254)
+        );
+        assert_eq!(sar(255.into(), MIN_NEGATIVE), U256::MAX);
+        assert_eq!(sar(256.into(), MIN_NEGATIVE), U256::MAX);
+        assert_eq!(sar(U256::zero(), U256::MAX), U256::MAX);
+        assert_eq!(sar(256.into(), U256::MAX), U256::MAX);
+        let max_positive = U256::MAX >> 1;
+        assert_eq!(sar(254.into(), max_positive), 1.into());
+        assert_eq!(sar(255.into(), max_positive), U256::zero());
+        assert_eq!(sar(U256::MAX, max_positive), U256::zero());
+    }
+}
@@ -0,0 +1,153 @@
+use bytes::Bytes;
+use primitive_types::U256;
+
+use crate::{keccak, Address};
+
+/// Frames can nest this deep below the top-level one; deeper calls fail.
+pub const MAX_CALL_DEPTH: usize = 1024;
+/// Largest code a contract can be deployed with (EIP-170).
+pub const MAX_CODE_SIZE: usize = 24576;
+/// Largest init code CREATE and CREATE2 accept (EIP-3860).
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+/// Which of the CALL or CREATE opcodes made a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallKind {
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+    Create,
+    Create2,
+}
+
+impl CallKind {
+    pub fn is_create(&self) -> bool {
+        matches!(self, CallKind::Create | CallKind::Create2)
+    }
+}
+
+/// A message call requested by a frame, which the `Evm` runs in a new frame on top of it.
+#[derive(Debug, Clone)]
+pub struct CallRequest {
+    pub kind: CallKind,
+    /// Account the callee runs as: its ADDRESS, storage and balance. For creations, the new
+    /// account.
+    pub address: Address,
+    /// Account the callee's code is loaded from. Differs from `address` for DELEGATECALL and
+    /// CALLCODE.
+    pub code_address: Address,
+    pub caller: Address,
+    pub value: U256,
+    /// Calldata, or the init code to run for creations.
+    pub input: Bytes,
+    pub gas_limit: u64,
+    pub is_static: bool,
+    /// Memory range of the caller that receives the output.
+    pub return_offset: usize,
+    pub return_size: usize,
+}
+
+impl CallRequest {
+    /// Whether `value` actually moves from `caller` to `address`. DELEGATECALL only passes
+    /// its caller's value along.
+    pub fn transfers_value(&self) -> bool {
+        !matches!(self.kind, CallKind::DelegateCall | CallKind::StaticCall)
+    }
+}
+
+/// Address of a contract created by CREATE: the last 20 bytes of
+/// `keccak256(rlp([sender, nonce]))`.
+pub fn create_address(sender: Address, nonce: u64) -> Address {
+    let nonce_bytes = nonce.to_be_bytes();
+    let nonce_bytes = &nonce_bytes[nonce.leading_zeros() as usize / 8..];
+    let mut rlp = Vec::with_capacity(30);
+    // Both items are short, so the list header is a single byte
+    let nonce_len = match nonce_bytes {
+        [byte] if *byte < 0x80 => 1,
+        _ => 1 + nonce_bytes.len(),
+    };
+    rlp.push(0xc0 + (1 + Address::len_bytes() + nonce_len) as u8);
+    rlp.push(0x80 + Address::len_bytes() as u8);
+    rlp.extend_from_slice(sender.as_bytes());
+    match nonce_bytes {
+        [byte] if *byte < 0x80 => rlp.push(*byte),
+        _ => {
+            rlp.push(0x80 + nonce_bytes.len() as u8);
+            rlp.extend_from_slice(nonce_bytes);
+        }
+    }
+    Address::from_slice(&keccak::keccak256(&rlp)[12..])
+}
+
+/// Address of a contract created by CREATE2: the last 20 bytes of
+/// `keccak256(0xff ++ sender ++ salt ++ keccak256(init_code))` (EIP-1014).
+pub fn create2_address(sender: Address, salt: U256, init_code: &[u8]) -> Address {
+    let mut preimage = Vec::with_capacity(85);
+    preimage.push(0xff);
+    preimage.extend_from_slice(sender.as_bytes());
+    let mut salt_bytes = [0u8; 32];
+    salt.to_big_endian(&mut salt_bytes);
+    preimage.extend_from_slice(&salt_bytes);
+    preimage.extend_from_slice(&keccak::keccak256(init_code));
+    Address::from_slice(&keccak::keccak256(&preimage)[12..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(hex_address: &str) -> Address {
+        Address::from_slice(&hex::decode(hex_address).unwrap())
+    }
+
+    #[test]
+    fn create_address_from_sender_and_nonce() {
+        let sender = address("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+        assert_eq!(
+            create_address(sender, 0),
+            address("cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d")
+        );
+        assert_eq!(
+            create_address(sender, 1),
+            address("343c43a37d37dff08ae8c4a11544c718abb4fcf8")
+        );
+        assert_eq!(
+            create_address(sender, 3),
+            address("fffd933a0bc612844eaf0c6fe3e5b8e9b6c1d19c")
+        );
+    }
+
+    #[test]
+    fn create_address_with_multi_byte_nonce() {
+        // rlp([sender, 0x0400]) = 0xd8 0x94 <sender> 0x82 0x04 0x00
+        let sender = address("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+        let mut rlp = vec![0xd8, 0x94];
+        rlp.extend_from_slice(sender.as_bytes());
+        rlp.extend_from_slice(&[0x82, 0x04, 0x00]);
+        assert_eq!(
+            create_address(sender, 0x400),
+            Address::from_slice(&keccak::keccak256(&rlp)[12..])
+        );
+    }
+
+    #[test]
+    fn create2_address_eip1014_examples() {
+        assert_eq!(
+            create2_address(Address::zero(), U256::zero(), &[0x00]),
+            address("4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38")
+        );
+        assert_eq!(
+            create2_address(
+                address("00000000000000000000000000000000deadbeef"),
+                0xcafebabe_u64.into(),
+                &[0xde, 0xad, 0xbe, 0xef]
+            ),
+            address("60f3f640a8508fc6a86d45df051962668e1e8ac7")
+        );
+        assert_eq!(
+            create2_address(Address::zero(), U256::zero(), &[]),
+            address("e33c0c7f7df4809055c3eba6c09cfe4baf1bd9e0")
+        );
+    }
+}
@@ -1,4 +1,6 @@
 use bytes::Bytes;
+use primitive_types::U256;
+
 #[derive(Debug)]
 pub struct Calldata {
     pub data: Bytes,
@@ -16,13 +18,14 @@ impl Calldata {
             0
         }
     }
-    // TODO: Change to handle U256
-    pub fn read_word(&self, offset: usize) -> u128 {
-        let mut bytes = [0u8; 16];
-        for i in offset..offset + 16 {
-            bytes[i] = self.read_byte(i) as u8
+
+    /// Reads a 32-byte word starting at `offset`, zero-padding bytes past the end of the data.
+    pub fn read_word(&self, offset: usize) -> U256 {
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = self.read_byte(offset.saturating_add(i));
         }
-        u128::from_be_bytes(bytes)
+        U256::from_big_endian(&bytes)
     }
 }
 
@@ -50,9 +53,9 @@ mod tests {
     }
     #[test]
     fn test_read_word() {
-        // Extended hex_data to 32 hex digits (16 bytes)
-        let hex_data = "0123456789abcdef0123456789abcdef";
-        let data = match hex::decode(&hex_data) {
+        // Extended hex_data to 64 hex digits (32 bytes)
+        let hex_data = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+        let data = match hex::decode(hex_data) {
             Ok(bytes) => Bytes::from(bytes),
             Err(e) => panic!("Failed to decode hex: {}", e),
         };
@@ -60,13 +63,9 @@ mod tests {
 
         let offset = 0;
 
-        // Calculate the expected u128 value from the 16 bytes starting at the offset
-        let expected_word = u128::from_be_bytes(
-            hex::decode(&hex_data[offset * 2..offset * 2 + 32])
-                .unwrap()
-                .try_into()
-                .unwrap(),
-        );
+        // Calculate the expected U256 value from the 32 bytes starting at the offset
+        let expected_word =
+            U256::from_big_endian(&hex::decode(&hex_data[offset * 2..offset * 2 + 64]).unwrap());
 
         let word = calldata.read_word(offset);
 
@@ -76,4 +75,14 @@ mod tests {
             offset
         );
     }
+
+    #[test]
+    fn test_read_word_past_end_is_zero_padded() {
+        let calldata = Calldata::new(Bytes::from(vec![0xaa, 0xbb]));
+
+        let mut expected = [0u8; 32];
+        expected[0] = 0xbb;
+        assert_eq!(calldata.read_word(1), U256::from_big_endian(&expected));
+        assert_eq!(calldata.read_word(64), U256::zero());
+    }
 }
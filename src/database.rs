@@ -0,0 +1,60 @@
+use primitive_types::{H256, U256};
+use std::{collections::HashMap, fmt::Debug};
+
+use crate::{account::Account, Address};
+
+/// Source of the world state the interpreter executes against. Implement it to run against
+/// state from somewhere other than memory, e.g. a fixture file or a node.
+pub trait Database: Debug {
+    /// The account at `address`, or `None` if it doesn't exist. Accounts are returned by
+    /// value so that implementations can load them lazily. `storage` may be left empty if
+    /// `Database::storage` is overridden.
+    fn account(&self, address: Address) -> Option<Account>;
+
+    fn storage(&self, address: Address, key: U256) -> U256 {
+        self.account(address)
+            .and_then(|account| account.storage.get(&key).copied())
+            .unwrap_or_default()
+    }
+
+    /// Hash of block `number`, or zero if it's unknown.
+    fn block_hash(&self, number: u64) -> H256;
+}
+
+/// A `Database` backed by hash maps.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryDb {
+    accounts: HashMap<Address, Account>,
+    block_hashes: HashMap<u64, H256>,
+}
+
+impl InMemoryDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert_account(&mut self, address: Address, account: Account) {
+        self.accounts.insert(address, account);
+    }
+
+    pub fn insert_block_hash(&mut self, number: u64, hash: H256) {
+        self.block_hashes.insert(number, hash);
+    }
+}
+
+impl Database for InMemoryDb {
+    fn account(&self, address: Address) -> Option<Account> {
+        self.accounts.get(&address).cloned()
+    }
+
+    fn storage(&self, address: Address, key: U256) -> U256 {
+        self.accounts
+            .get(&address)
+            .and_then(|account| account.storage.get(&key).copied())
+            .unwrap_or_default()
+    }
+
+    fn block_hash(&self, number: u64) -> H256 {
+        self.block_hashes.get(&number).copied().unwrap_or_default()
+    }
+}
@@ -0,0 +1,66 @@
+use bytes::Bytes;
+use primitive_types::{H256, U256};
+
+use crate::Address;
+
+/// Block the transaction is executed in, read by COINBASE, TIMESTAMP, NUMBER and friends.
+#[derive(Debug, Clone)]
+pub struct BlockEnv {
+    pub number: U256,
+    pub coinbase: Address,
+    pub timestamp: U256,
+    pub gas_limit: U256,
+    pub basefee: U256,
+    pub prevrandao: H256,
+    pub blob_basefee: U256,
+}
+
+impl Default for BlockEnv {
+    fn default() -> Self {
+        Self {
+            number: U256::zero(),
+            coinbase: Address::zero(),
+            timestamp: U256::zero(),
+            gas_limit: 30_000_000.into(),
+            basefee: U256::zero(),
+            prevrandao: H256::zero(),
+            // EIP-4844 floors the blob base fee at 1 wei
+            blob_basefee: U256::one(),
+        }
+    }
+}
+
+/// The transaction being executed, read by ORIGIN, GASPRICE, CHAINID and the top-level frame.
+#[derive(Debug, Clone)]
+pub struct TxEnv {
+    /// Sender of the transaction, i.e. ORIGIN and the CALLER of the top-level frame.
+    pub caller: Address,
+    pub gas_price: U256,
+    pub gas_limit: u64,
+    pub value: U256,
+    pub data: Bytes,
+    pub chain_id: u64,
+    /// Accounts and slots to warm up before execution (EIP-2930).
+    pub access_list: Vec<AccessListItem>,
+}
+
+/// An account, and some of its slots, that a transaction declares it will access.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessListItem {
+    pub address: Address,
+    pub storage_keys: Vec<U256>,
+}
+
+impl Default for TxEnv {
+    fn default() -> Self {
+        Self {
+            caller: Address::zero(),
+            gas_price: U256::zero(),
+            gas_limit: 30_000_000,
+            value: U256::zero(),
+            data: Bytes::new(),
+            chain_id: 1,
+            access_list: Vec::new(),
+        }
+    }
+}
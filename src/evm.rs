@@ -0,0 +1,613 @@
+use bytes::Bytes;
+use primitive_types::U256;
+
+use std::rc::Rc;
+
+use crate::{
+    call::{CallRequest, MAX_CODE_SIZE},
+    database::{Database, InMemoryDb},
+    env::{BlockEnv, TxEnv},
+    execution_context::ExecutionContext,
+    execution_result::{ExecutionError, ExecutionResult, ExecutionStatus},
+    gas,
+    instruction::{Instruction, InstructionTable},
+    opcodes::{Errors, Opcodes},
+    state::{Checkpoint, State},
+    storage::Storage,
+    Address,
+};
+
+/// Precompiles live at addresses 0x01 to 0x0a as of Cancun. They are not implemented, but
+/// they are still warm from the start.
+const PRECOMPILE_COUNT: u64 = 10;
+
+/// Drives an `ExecutionContext` to completion, one instruction at a time.
+#[derive(Debug)]
+pub struct Evm {
+    /// The top-level frame.
+    pub context: ExecutionContext,
+    /// World state, shared by every frame.
+    pub state: State,
+    /// State before the transaction, reverted to if it fails.
+    checkpoint: Checkpoint,
+    /// Frames of the calls in progress, innermost last.
+    frames: Vec<Frame>,
+    instructions: InstructionTable,
+    steps: usize,
+    halt: Option<ExecutionError>,
+}
+
+/// A call frame above the top-level one.
+#[derive(Debug)]
+struct Frame {
+    context: ExecutionContext,
+    call: CallRequest,
+    /// Reverted to if the call fails.
+    checkpoint: Checkpoint,
+    halt: Option<ExecutionError>,
+}
+
+#[derive(Debug)]
+pub struct EvmBuilder {
+    code: Bytes,
+    tx_env: TxEnv,
+    block_env: BlockEnv,
+    address: Address,
+    db: Rc<dyn Database>,
+    storage: Storage,
+    instructions: Option<InstructionTable>,
+}
+
+impl Default for EvmBuilder {
+    fn default() -> Self {
+        Self {
+            code: Bytes::new(),
+            tx_env: TxEnv::default(),
+            block_env: BlockEnv::default(),
+            address: Address::zero(),
+            db: Rc::new(InMemoryDb::new()),
+            storage: Storage::new(),
+            instructions: None,
+        }
+    }
+}
+
+impl EvmBuilder {
+    pub fn code(mut self, code: Bytes) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// Shorthand for setting `TxEnv::data`.
+    pub fn calldata(mut self, calldata: Bytes) -> Self {
+        self.tx_env.data = calldata;
+        self
+    }
+
+    /// Shorthand for setting `TxEnv::value`.
+    pub fn call_value(mut self, call_value: U256) -> Self {
+        self.tx_env.value = call_value;
+        self
+    }
+
+    /// Shorthand for setting `TxEnv::gas_limit`.
+    pub fn gas_limit(mut self, gas_limit: u64) -> Self {
+        self.tx_env.gas_limit = gas_limit;
+        self
+    }
+
+    /// Shorthand for setting `TxEnv::caller`.
+    pub fn caller(mut self, caller: Address) -> Self {
+        self.tx_env.caller = caller;
+        self
+    }
+
+    /// The transaction being executed. Replaces anything set through the shorthands above.
+    pub fn tx_env(mut self, tx_env: TxEnv) -> Self {
+        self.tx_env = tx_env;
+        self
+    }
+
+    pub fn block_env(mut self, block_env: BlockEnv) -> Self {
+        self.block_env = block_env;
+        self
+    }
+
+    /// Address of the account the code runs as.
+    pub fn address(mut self, address: Address) -> Self {
+        self.address = address;
+        self
+    }
+
+    /// World state to execute against, an empty `InMemoryDb` by default.
+    pub fn database<D: Database + 'static>(mut self, db: D) -> Self {
+        self.db = Rc::new(db);
+        self
+    }
+
+    /// Pre-state storage, seeded with `Storage::insert`.
+    pub fn storage(mut self, storage: Storage) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    /// Replaces the default `Opcodes` table, e.g. to disable or override opcodes.
+    pub fn instruction_table(mut self, instructions: InstructionTable) -> Self {
+        self.instructions = Some(instructions);
+        self
+    }
+
+    /// Warms what a transaction can access at no extra cost: the sender, the recipient and the
+    /// precompiles (EIP-2929), the coinbase (EIP-3651) and its access list (EIP-2930).
+    fn warm_up(context: &ExecutionContext, state: &mut State) {
+        state.warm_address(context.tx_env.caller);
+        state.warm_address(context.address);
+        state.warm_address(context.block_env.coinbase);
+        for precompile in 1..=PRECOMPILE_COUNT {
+            state.warm_address(Address::from_low_u64_be(precompile));
+        }
+        for item in &context.tx_env.access_list {
+            state.warm_address(item.address);
+            for &key in &item.storage_keys {
+                state.warm_slot(item.address, key);
+            }
+        }
+    }
+
+    pub fn build(self) -> Evm {
+        let tx_env = self.tx_env;
+        let mut context = ExecutionContext::new(self.code, tx_env.data.clone(), tx_env.gas_limit);
+        context.callvalue = tx_env.value;
+        context.caller = tx_env.caller;
+        context.address = self.address;
+        context.block_env = self.block_env;
+        context.tx_env = tx_env;
+        let mut state = State::new(self.db);
+        state.storage = self.storage;
+        Self::warm_up(&context, &mut state);
+        let checkpoint = state.checkpoint();
+
+        Evm {
+            context,
+            state,
+            checkpoint,
+            frames: Vec::new(),
+            instructions: self.instructions.unwrap_or_else(Opcodes::instruction_table),
+            steps: 0,
+            halt: None,
+        }
+    }
+}
+
+impl Evm {
+    pub fn builder() -> EvmBuilder {
+        EvmBuilder::default()
+    }
+
+    /// Returns true once the execution has stopped, either normally or by halting.
+    pub fn is_stopped(&self) -> bool {
+        self.context.stopped
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Number of call frames above the top-level one.
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The frame executing right now: the innermost call, or the top-level frame.
+    pub fn current_context(&self) -> &ExecutionContext {
+        self.frames
+            .last()
+            .map_or(&self.context, |frame| &frame.context)
+    }
+
+    /// Executes a single instruction in the current frame, entering or leaving call frames as
+    /// needed. An error halts that frame for good. Once the execution has stopped this does
+    /// nothing and returns `Ok(None)`.
+    pub fn step(&mut self) -> Result<Option<&Instruction>, ExecutionError> {
+        if self.is_stopped() {
+            return Ok(None);
+        }
+        self.steps += 1;
+        let (context, halt) = match self.frames.last_mut() {
+            Some(frame) => (&mut frame.context, &mut frame.halt),
+            None => (&mut self.context, &mut self.halt),
+        };
+        let state = &mut self.state;
+        let result = context
+            .step(&self.instructions, state)
+            .map_err(|error| Self::halt(context, halt, error));
+
+        if let Some(call) = context.call.take() {
+            Self::enter(&mut self.context, &mut self.frames, state, call);
+        } else if context.stopped {
+            match self.frames.pop() {
+                Some(frame) => Self::exit(&mut self.context, &mut self.frames, state, frame),
+                None => {
+                    // A failed transaction leaves no trace in the state
+                    if self.halt.is_some() || self.context.reverted {
+                        state.revert_to(self.checkpoint);
+                    } else {
+                        state.commit(self.checkpoint);
+                    }
+                    state.finish_transaction();
+                }
+            }
+        }
+        result.map(Some)
+    }
+
+    /// Runs until the execution stops.
+    pub fn run(&mut self) -> ExecutionResult {
+        while !self.is_stopped() {
+            let _ = self.step();
+        }
+        self.result()
+    }
+
+    /// Runs until `predicate` holds for the context about to be stepped, or the execution stops.
+    /// Returns the result only if the execution stopped.
+    pub fn run_until<F>(&mut self, mut predicate: F) -> Option<ExecutionResult>
+    where
+        F: FnMut(&ExecutionContext) -> bool,
+    {
+        while !self.is_stopped() {
+            if predicate(self.current_context()) {
+                return None;
+            }
+            let _ = self.step();
+        }
+        Some(self.result())
+    }
+
+    /// The outcome of the execution so far.
+    pub fn result(&self) -> ExecutionResult {
+        let status = match &self.halt {
+            Some(error) => ExecutionStatus::Halt(error.clone()),
+            None if self.context.reverted => ExecutionStatus::Revert,
+            None => ExecutionStatus::Success,
+        };
+        ExecutionResult::new(
+            status,
+            self.context.returndata.clone(),
+            self.context.gas,
+            self.steps,
+        )
+        .with_storage_changes(self.state.storage.diff())
+        .with_logs(self.state.logs.clone())
+    }
+
+    /// Pushes a frame for `call` on top of the current one.
+    fn enter(
+        root: &mut ExecutionContext,
+        frames: &mut Vec<Frame>,
+        state: &mut State,
+        call: CallRequest,
+    ) {
+        let parent = frames.last_mut().map_or(root, |frame| &mut frame.context);
+        let checkpoint = state.checkpoint();
+
+        // A creation runs its init code, without calldata
+        let (code, calldata) = if call.kind.is_create() {
+            (call.input.clone(), Bytes::new())
+        } else {
+            (state.code(call.code_address), call.input.clone())
+        };
+        let mut context = ExecutionContext::new(code, calldata, call.gas_limit);
+        context.address = call.address;
+        context.caller = call.caller;
+        context.callvalue = call.value;
+        context.block_env = parent.block_env.clone();
+        context.tx_env = parent.tx_env.clone();
+        context.depth = parent.depth + 1;
+        context.is_static = call.is_static;
+        if call.kind.is_create() {
+            state.create_account(call.address);
+        }
+        if call.transfers_value() {
+            let transferred = state.transfer(call.caller, call.address, call.value);
+            assert!(
+                transferred,
+                "the caller checked the balance before asking for the call"
+            );
+        }
+
+        frames.push(Frame {
+            context,
+            call,
+            checkpoint,
+            halt: None,
+        });
+    }
+
+    /// Returns from a finished call `frame` to its caller: undoes the call's changes if it
+    /// failed, hands back unused gas and output, and pushes the success flag.
+    fn exit(root: &mut ExecutionContext, frames: &mut [Frame], state: &mut State, frame: Frame) {
+        let parent = frames.last_mut().map_or(root, |frame| &mut frame.context);
+        let Frame {
+            mut context,
+            call,
+            checkpoint,
+            mut halt,
+        } = frame;
+
+        if call.kind.is_create() && halt.is_none() && !context.reverted {
+            if let Err(error) = Self::deposit_code(&mut context, state, call.address) {
+                context.gas.consume_all();
+                halt = Some(error);
+            }
+        }
+
+        let success = halt.is_none() && !context.reverted;
+        parent.gas.return_gas(context.gas.remaining());
+        if success {
+            parent.gas.record_refund(context.gas.refunded());
+            state.commit(checkpoint);
+        } else {
+            state.revert_to(checkpoint);
+        }
+
+        // An exceptional halt has no output, while REVERT keeps it. The output of a
+        // successful creation is its code, which isn't returned to the creator.
+        let output = match halt {
+            Some(_) => Bytes::new(),
+            None if success && call.kind.is_create() => Bytes::new(),
+            None => context.returndata,
+        };
+        let length = output.len().min(call.return_size);
+        parent
+            .memory
+            .store_range(call.return_offset, &output[..length]);
+        parent.returndata_buffer = output;
+
+        let result = match (success, call.kind.is_create()) {
+            (true, true) => U256::from_big_endian(call.address.as_bytes()),
+            (true, false) => U256::one(),
+            (false, _) => U256::zero(),
+        };
+        parent
+            .stack
+            .push(result)
+            .expect("the call popped its arguments off this stack");
+    }
+
+    /// Stores the output of a successful creation as the code of the new account, after
+    /// checking it against EIP-170 and EIP-3541 and charging for it.
+    fn deposit_code(
+        context: &mut ExecutionContext,
+        state: &mut State,
+        address: Address,
+    ) -> Result<(), ExecutionError> {
+        let code = context.returndata.clone();
+        if code.len() > MAX_CODE_SIZE {
+            return Err(Errors::CodeTooLarge(code.len()).into());
+        }
+        if code.first() == Some(&0xef) {
+            return Err(Errors::InvalidCodePrefix.into());
+        }
+        context.gas.charge(gas::code_deposit_cost(code.len()))?;
+        state.account_mut(address).set_code(code);
+        Ok(())
+    }
+
+    fn halt(
+        context: &mut ExecutionContext,
+        halt: &mut Option<ExecutionError>,
+        error: ExecutionError,
+    ) -> ExecutionError {
+        context.stop();
+        context.gas.consume_all();
+        *halt = Some(error.clone());
+        error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::Account;
+
+    fn evm_for(hex_code: &str) -> Evm {
+        Evm::builder()
+            .code(Bytes::from(hex::decode(hex_code).unwrap()))
+            .build()
+    }
+
+    #[test]
+    fn run_to_completion() {
+        // Four squared, see README
+        let mut evm = evm_for("60048060005b8160125760005360016000f35b8201906001900390600556");
+        let result = evm.run();
+        assert!(result.is_success());
+        assert_eq!(result.returndata.as_ref(), &[0x10]);
+    }
+
+    #[test]
+    fn step_after_stop_changes_nothing() {
+        // PUSH0 PUSH0 RETURN PUSH1 0x01 PUSH0 SSTORE
+        let mut evm = evm_for("5f5ff360015f55");
+        assert!(evm.run().is_success());
+        for _ in 0..3 {
+            assert!(matches!(evm.step(), Ok(None)));
+        }
+        let result = evm.result();
+        assert_eq!(result.steps, 3);
+        assert!(result.storage_changes.is_empty());
+    }
+
+    #[test]
+    fn state_is_visible_while_paused_in_a_call() {
+        let caller = Address::from_low_u64_be(0xc0);
+        let callee = Address::from_low_u64_be(0xaa);
+        let mut db = InMemoryDb::new();
+        db.insert_account(caller, Account::new(100.into()));
+        db.insert_account(
+            callee,
+            Account::default().with_code(Bytes::from_static(&[0x00])),
+        );
+        // PUSH1 0x01 PUSH0 SSTORE CALL(0xffff, 0xaa, 7, 0, 0, 0, 0) STOP
+        let mut evm = Evm::builder()
+            .code(Bytes::from(
+                hex::decode("60015f555f5f5f5f600760aa61fffff100").unwrap(),
+            ))
+            .address(caller)
+            .database(db)
+            .build();
+
+        assert!(evm.run_until(|context| context.depth == 1).is_none());
+        assert_eq!(evm.depth(), 1);
+        assert_eq!(evm.state.balance(caller), 93.into());
+        assert_eq!(evm.state.balance(callee), 7.into());
+        let changes = evm.result().storage_changes;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            (changes[0].address, changes[0].current),
+            (caller, U256::one())
+        );
+
+        assert!(evm.run().is_success());
+        assert_eq!(evm.state.balance(callee), 7.into());
+    }
+
+    #[test]
+    fn run_until_pauses_on_predicate() {
+        // PUSH1 0x01 PUSH1 0x02 ADD STOP
+        let mut evm = evm_for("600160020100");
+        assert!(evm.run_until(|context| context.pc == 4).is_none());
+        assert_eq!(evm.steps(), 2);
+        assert_eq!(evm.context.stack.stack.len(), 2);
+
+        let result = evm.run_until(|_| false).unwrap();
+        assert!(result.is_success());
+        assert_eq!(evm.context.stack.peek(0).unwrap(), 3.into());
+    }
+
+    #[test]
+    fn reports_gas_used_and_remaining() {
+        // PUSH1 0x01 PUSH1 0x02 ADD STOP
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("600160020100").unwrap()))
+            .gas_limit(100)
+            .build();
+        let result = evm.run();
+        assert!(result.is_success());
+        assert_eq!(result.gas_used, 9);
+        assert_eq!(result.gas_remaining, 91);
+    }
+
+    #[test]
+    fn infinite_loop_runs_out_of_gas() {
+        // JUMPDEST PUSH1 0x00 JUMP
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("5b600056").unwrap()))
+            .gas_limit(120)
+            .build();
+        let result = evm.run();
+        assert!(matches!(
+            result.status,
+            ExecutionStatus::Halt(ExecutionError::OutOfGas)
+        ));
+        assert_eq!(result.gas_used, 120);
+        assert_eq!(result.gas_remaining, 0);
+        // Each iteration costs 1 + 3 + 8 gas
+        assert_eq!(result.steps, 31);
+    }
+
+    #[test]
+    fn memory_expansion_is_charged() {
+        // PUSH1 0x01 PUSH1 0x3f MSTORE8 PUSH1 0x40 PUSH0 RETURN
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("6001603f5360405ff3").unwrap()))
+            .build();
+        let result = evm.run();
+        assert!(result.is_success());
+        // Static costs plus 2 words of memory, charged once by MSTORE8
+        assert_eq!(result.gas_used, 3 + 3 + 3 + 3 + 2 + 2 * 3);
+        assert_eq!(evm.context.memory.active_words(), 2);
+    }
+
+    #[test]
+    fn hostile_memory_offset_runs_out_of_gas() {
+        // PUSH1 0x01 PUSH6 0x010000000000 MSTORE8
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("60016501000000000053").unwrap()))
+            .build();
+        let result = evm.run();
+        assert!(matches!(
+            result.status,
+            ExecutionStatus::Halt(ExecutionError::OutOfGas)
+        ));
+        assert_eq!(evm.context.memory.active_words(), 0);
+    }
+
+    #[test]
+    fn zero_length_return_ignores_offset() {
+        // PUSH0 PUSH32 0xff..ff RETURN
+        let mut evm = Evm::builder()
+            .code(Bytes::from(
+                hex::decode(format!("5f7f{}f3", "ff".repeat(32))).unwrap(),
+            ))
+            .build();
+        let result = evm.run();
+        assert!(result.is_success());
+        assert!(result.returndata.is_empty());
+    }
+
+    #[test]
+    fn exceptional_halt_consumes_all_gas() {
+        // ADD on an empty stack
+        let mut evm = Evm::builder()
+            .code(Bytes::from(vec![0x01]))
+            .gas_limit(100)
+            .build();
+        let result = evm.run();
+        assert!(!result.is_success());
+        assert_eq!(result.gas_used, 100);
+    }
+
+    #[test]
+    fn custom_instruction_table() {
+        let mut instructions = Opcodes::instruction_table();
+        instructions.remove_instruction(0x01);
+        // PUSH1 0x01 PUSH1 0x02 ADD
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("6001600201").unwrap()))
+            .instruction_table(instructions)
+            .build();
+        let result = evm.run();
+        assert!(matches!(
+            result.status,
+            ExecutionStatus::Halt(ExecutionError::Instruction(
+                crate::instruction::InstructionError::OpcodeNotFound(0x01)
+            ))
+        ));
+    }
+
+    #[test]
+    fn transient_storage_is_cleared_after_run() {
+        // PUSH1 0x2a PUSH1 0x01 TSTORE PUSH1 0x01 TLOAD
+        let mut evm = evm_for("602a60015d60015c");
+        let result = evm.run();
+        assert!(result.is_success());
+        assert_eq!(evm.context.stack.stack, vec![U256::from(0x2a)]);
+        assert!(evm.state.transient_storage.is_empty());
+        assert!(result.storage_changes.is_empty());
+        assert_eq!(result.gas_used, 3 + 3 + 100 + 3 + 100);
+    }
+
+    #[test]
+    fn call_value_and_calldata_are_configurable() {
+        // CALLVALUE PUSH0 CALLDATALOAD
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("345f35").unwrap()))
+            .calldata(Bytes::from(vec![0xff; 32]))
+            .call_value(7.into())
+            .build();
+        evm.run();
+        assert_eq!(evm.context.stack.stack, vec![U256::from(7), U256::MAX]);
+    }
+}
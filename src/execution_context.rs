@@ -1,10 +1,18 @@
 use crate::{
+    call::CallRequest,
     calldata::Calldata,
-    instruction::{Instruction, INSTRUCTIONS_BY_OPCODE},
+    env::{BlockEnv, TxEnv},
+    execution_result::ExecutionError,
+    gas::{self, Gas},
+    instruction::{Instruction, InstructionTable},
     memory::Memory,
+    opcodes::Errors,
     stack::Stack,
+    state::State,
+    Address,
 };
 use bytes::Bytes;
+use primitive_types::U256;
 
 #[derive(Debug)]
 pub struct ExecutionContext {
@@ -13,40 +21,120 @@ pub struct ExecutionContext {
     pub memory: Memory,
     pub pc: usize,
     pub stopped: bool,
+    /// Set when the execution stopped through REVERT.
+    pub reverted: bool,
     pub returndata: Bytes,
+    /// Output of the most recent sub-call, read by RETURNDATASIZE and RETURNDATACOPY.
+    pub returndata_buffer: Bytes,
     pub jumpdests: Vec<usize>,
     pub calldata: Calldata,
+    pub callvalue: U256,
+    pub gas: Gas,
+    /// Account whose storage is being executed against.
+    pub address: Address,
+    /// Immediate sender of this frame, as opposed to `tx_env.caller`.
+    pub caller: Address,
+    pub block_env: BlockEnv,
+    pub tx_env: TxEnv,
+    /// Number of frames below this one; the top-level frame is at depth 0.
+    pub depth: usize,
+    /// Set inside STATICCALL, where nothing may modify state.
+    pub is_static: bool,
+    /// Call requested by the last instruction, picked up by the `Evm`.
+    pub call: Option<CallRequest>,
 }
 
 impl ExecutionContext {
-    pub fn new(code: Bytes) -> Self {
+    pub fn new(code: Bytes, calldata: Bytes, gas_limit: u64) -> Self {
         Self {
             code: code.clone(),
             stack: Stack::new(1024),
             memory: Memory::new(),
             pc: 0,
             stopped: false,
+            reverted: false,
             returndata: Bytes::new(),
+            returndata_buffer: Bytes::new(),
             jumpdests: Self::valid_jump_destinations(code.clone()),
-            calldata: Calldata::new(code),
+            calldata: Calldata::new(calldata),
+            callvalue: U256::zero(),
+            gas: Gas::new(gas_limit),
+            address: Address::zero(),
+            caller: Address::zero(),
+            block_env: BlockEnv::default(),
+            tx_env: TxEnv::default(),
+            depth: 0,
+            is_static: false,
+            call: None,
         }
     }
 
+    /// Decodes the instruction at the current pc using `instructions`, charges its static gas
+    /// and executes it against `state`.
+    pub fn step<'a>(
+        &mut self,
+        instructions: &'a InstructionTable,
+        state: &mut State,
+    ) -> Result<&'a Instruction, ExecutionError> {
+        let instruction = instructions.decode_opcode(self)?;
+        self.gas.charge(instruction.gas_cost)?;
+        instruction.executor.execute(self, state)?;
+        Ok(instruction)
+    }
+
+    /// Fails if this frame runs inside a STATICCALL.
+    pub fn ensure_mutable(&self) -> Result<(), Errors> {
+        if self.is_static {
+            return Err(Errors::StateChangeInStaticCall);
+        }
+        Ok(())
+    }
+
     pub fn stop(&mut self) {
         self.stopped = true;
     }
 
     /// Returns the next num_bytes from the code buffer as an integer and advances pc by num_bytes.
-    pub fn read_code(&mut self, num_bytes: usize) -> usize {
-        let bytes_slice = &self.code[self.pc..self.pc + num_bytes];
+    /// Bytes past the end of the code are read as zero.
+    pub fn read_code(&mut self, num_bytes: usize) -> U256 {
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes[32 - num_bytes..].iter_mut().enumerate() {
+            *byte = self.code.get(self.pc + i).copied().unwrap_or(0);
+        }
         self.pc += num_bytes;
 
-        let mut result: usize = 0;
-        for &byte in bytes_slice.iter() {
-            result = (result << 8) | (byte as usize);
+        U256::from_big_endian(&bytes)
+    }
+
+    /// Charges the expansion fee for accessing `size` bytes at `offset`, then grows memory.
+    /// The fee is computed from the new high-water mark before anything is allocated, so
+    /// unreasonable offsets run out of gas. Returns the range as usize; a zero-sized access
+    /// never expands memory and yields offset 0.
+    pub fn expand_memory(
+        &mut self,
+        offset: U256,
+        size: U256,
+    ) -> Result<(usize, usize), ExecutionError> {
+        if size.is_zero() {
+            return Ok((0, 0));
+        }
+        let end = match offset.checked_add(size) {
+            Some(end) if end <= U256::from(u64::MAX) => end.as_u64(),
+            _ => return Err(self.out_of_gas()),
+        };
+        let new_words = end.div_ceil(32);
+        let cost = gas::memory_expansion_cost(self.memory.active_words() as u64, new_words);
+        match u64::try_from(cost) {
+            Ok(cost) => self.gas.charge(cost)?,
+            Err(_) => return Err(self.out_of_gas()),
         }
+        self.memory.expand_if_needed(end as usize - 1);
+        Ok((offset.as_usize(), size.as_usize()))
+    }
 
-        result
+    fn out_of_gas(&mut self) -> ExecutionError {
+        self.gas.consume_all();
+        ExecutionError::OutOfGas
     }
 
     pub fn set_returndata(&mut self, offset: usize, length: usize) {
@@ -54,10 +142,27 @@ impl ExecutionContext {
         self.returndata = self.memory.load_range(offset, length);
     }
 
+    /// Stops with the given memory range as output, flagging the execution as failed.
+    pub fn revert(&mut self, offset: usize, length: usize) {
+        self.set_returndata(offset, length);
+        self.reverted = true;
+    }
+
     pub fn set_pc(&mut self, pc: usize) {
         self.pc = pc;
     }
 
+    /// Moves pc to `target`, which must be a JUMPDEST.
+    pub fn jump(&mut self, target: U256) -> Result<(), Errors> {
+        match usize::try_from(target) {
+            Ok(pc) if self.jumpdests.contains(&pc) => {
+                self.set_pc(pc);
+                Ok(())
+            }
+            _ => Err(Errors::InvalidJumpDestination(target)),
+        }
+    }
+
     pub fn valid_jump_destinations(code: Bytes) -> Vec<usize> {
         let mut jumpdests: Vec<usize> = Vec::new();
 
@@ -66,7 +171,7 @@ impl ExecutionContext {
             let current_op = code[i] as usize;
             if current_op == 0x5B {
                 jumpdests.push(i);
-            } else if 0x60 <= current_op && current_op <= 0x7F {
+            } else if (0x60..=0x7F).contains(&current_op) {
                 i += current_op - 0x60 + 1
             }
             i += 1;
@@ -74,3 +179,88 @@ impl ExecutionContext {
         jumpdests
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{opcodes::Opcodes, stack::StackError};
+
+    fn context_for(hex_code: &str) -> ExecutionContext {
+        ExecutionContext::new(
+            Bytes::from(hex::decode(hex_code).unwrap()),
+            Bytes::new(),
+            1_000_000,
+        )
+    }
+
+    #[test]
+    fn step_reports_stack_underflow() {
+        // ADD on an empty stack
+        let mut context = context_for("01");
+        assert!(matches!(
+            context.step(&Opcodes::instruction_table(), &mut State::default()),
+            Err(ExecutionError::Stack(StackError::StackUnderflow))
+        ));
+    }
+
+    #[test]
+    fn step_reports_invalid_jump() {
+        // PUSH1 0x03 JUMP STOP
+        let instructions = Opcodes::instruction_table();
+        let mut state = State::default();
+        let mut context = context_for("60035600");
+        context.step(&instructions, &mut state).unwrap();
+        assert!(matches!(
+            context.step(&instructions, &mut state),
+            Err(ExecutionError::Opcode(Errors::InvalidJumpDestination(_)))
+        ));
+    }
+
+    #[test]
+    fn step_reports_unknown_opcode() {
+        let mut context = context_for("0c");
+        assert!(matches!(
+            context.step(&Opcodes::instruction_table(), &mut State::default()),
+            Err(ExecutionError::Instruction(
+                crate::instruction::InstructionError::OpcodeNotFound(0x0c)
+            ))
+        ));
+    }
+
+    #[test]
+    fn calldata_is_separate_from_code() {
+        // CALLDATASIZE PUSH0 CALLDATALOAD
+        let instructions = Opcodes::instruction_table();
+        let mut state = State::default();
+        let mut context =
+            ExecutionContext::new(Bytes::from(vec![0x36, 0x5f, 0x35]), Bytes::new(), 1_000_000);
+        for _ in 0..3 {
+            context.step(&instructions, &mut state).unwrap();
+        }
+        assert_eq!(context.stack.stack, vec![U256::zero(), U256::zero()]);
+    }
+
+    #[test]
+    fn step_charges_static_gas() {
+        // PUSH1 0x01 PUSH1 0x02 MUL
+        let instructions = Opcodes::instruction_table();
+        let mut state = State::default();
+        let mut context = context_for("6001600202");
+        for _ in 0..3 {
+            context.step(&instructions, &mut state).unwrap();
+        }
+        assert_eq!(context.gas.used(), 3 + 3 + 5);
+    }
+
+    #[test]
+    fn sub_wraps_on_underflow() {
+        // PUSH1 0x01 PUSH1 0x00 SUB
+        let instructions = Opcodes::instruction_table();
+        let mut state = State::default();
+        let mut context = context_for("6001600003");
+        for _ in 0..3 {
+            context.step(&instructions, &mut state).unwrap();
+        }
+        assert_eq!(context.stack.pop().unwrap(), U256::MAX);
+    }
+}
@@ -0,0 +1,230 @@
+use bytes::Bytes;
+use primitive_types::U256;
+
+use crate::{
+    gas::Gas, instruction::InstructionError, log::Log, opcodes, stack::StackError,
+    storage::StorageChange,
+};
+
+/// Every reason the interpreter can halt exceptionally.
+#[derive(Debug, Clone)]
+pub enum ExecutionError {
+    Stack(StackError),
+    Instruction(InstructionError),
+    Opcode(opcodes::Errors),
+    OutOfGas,
+}
+
+impl From<StackError> for ExecutionError {
+    fn from(error: StackError) -> Self {
+        ExecutionError::Stack(error)
+    }
+}
+
+impl From<InstructionError> for ExecutionError {
+    fn from(error: InstructionError) -> Self {
+        ExecutionError::Instruction(error)
+    }
+}
+
+impl From<opcodes::Errors> for ExecutionError {
+    fn from(error: opcodes::Errors) -> Self {
+        ExecutionError::Opcode(error)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ExecutionStatus {
+    Success,
+    /// Stopped by REVERT: output is kept but state changes are dropped.
+    Revert,
+    /// Stopped by an exceptional halt: all gas is consumed and there is no output.
+    Halt(ExecutionError),
+}
+
+/// A revert payload in one of the standard Solidity encodings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertReason {
+    /// `Error(string)`, from `require` and `revert` with a message.
+    Error(String),
+    /// `Panic(uint256)`, from failed assertions, arithmetic overflow and the like.
+    Panic(U256),
+}
+
+impl RevertReason {
+    const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+    const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+    /// Decodes `Error(string)` or `Panic(uint256)` revert data, if that's what it holds.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 4 {
+            return None;
+        }
+        let (selector, payload) = data.split_at(4);
+        if selector == Self::PANIC_SELECTOR {
+            let code = payload.get(..32)?;
+            return Some(RevertReason::Panic(U256::from_big_endian(code)));
+        }
+        if selector != Self::ERROR_SELECTOR {
+            return None;
+        }
+
+        let read_usize = |offset: usize| -> Option<usize> {
+            let word = U256::from_big_endian(payload.get(offset..offset.checked_add(32)?)?);
+            usize::try_from(word).ok()
+        };
+        let string_offset = read_usize(0)?;
+        let length = read_usize(string_offset)?;
+        let start = string_offset.checked_add(32)?;
+        let bytes = payload.get(start..start.checked_add(length)?)?;
+        Some(RevertReason::Error(
+            String::from_utf8_lossy(bytes).into_owned(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecutionResult {
+    pub status: ExecutionStatus,
+    pub returndata: Bytes,
+    pub gas_used: u64,
+    pub gas_remaining: u64,
+    pub gas_refunded: u64,
+    pub steps: usize,
+    /// Storage slots changed by a successful run.
+    pub storage_changes: Vec<StorageChange>,
+    /// Events emitted by a successful run.
+    pub logs: Vec<Log>,
+}
+
+impl ExecutionResult {
+    pub fn new(status: ExecutionStatus, returndata: Bytes, gas: Gas, steps: usize) -> Self {
+        // Only a successful run earns a refund, and an exceptional halt returns no data.
+        let (returndata, gas_refunded) = match status {
+            ExecutionStatus::Success => (returndata, gas.final_refund()),
+            ExecutionStatus::Revert => (returndata, 0),
+            ExecutionStatus::Halt(_) => (Bytes::new(), 0),
+        };
+        Self {
+            status,
+            returndata,
+            gas_used: gas.used() - gas_refunded,
+            gas_remaining: gas.remaining() + gas_refunded,
+            gas_refunded,
+            steps,
+            storage_changes: Vec::new(),
+            logs: Vec::new(),
+        }
+    }
+
+    /// Attaches the storage diff, which only applies if the run succeeded.
+    pub fn with_storage_changes(mut self, storage_changes: Vec<StorageChange>) -> Self {
+        if self.is_success() {
+            self.storage_changes = storage_changes;
+        }
+        self
+    }
+
+    /// Attaches the emitted logs, which only survive if the run succeeded.
+    pub fn with_logs(mut self, logs: Vec<Log>) -> Self {
+        if self.is_success() {
+            self.logs = logs;
+        }
+        self
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self.status, ExecutionStatus::Success)
+    }
+
+    pub fn is_revert(&self) -> bool {
+        matches!(self.status, ExecutionStatus::Revert)
+    }
+
+    pub fn is_halt(&self) -> bool {
+        matches!(self.status, ExecutionStatus::Halt(_))
+    }
+
+    /// The decoded revert reason, if the run reverted with a standard payload.
+    pub fn revert_reason(&self) -> Option<RevertReason> {
+        if !self.is_revert() {
+            return None;
+        }
+        RevertReason::decode(&self.returndata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_error_string() {
+        // revert("Not enough Ether provided.")
+        let data = hex::decode(concat!(
+            "08c379a0",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "000000000000000000000000000000000000000000000000000000000000001a",
+            "4e6f7420656e6f7567682045746865722070726f76696465642e000000000000",
+        ))
+        .unwrap();
+        assert_eq!(
+            RevertReason::decode(&data),
+            Some(RevertReason::Error(
+                "Not enough Ether provided.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn decode_panic_code() {
+        // Panic(0x11), arithmetic overflow
+        let data = hex::decode(concat!(
+            "4e487b71",
+            "0000000000000000000000000000000000000000000000000000000000000011",
+        ))
+        .unwrap();
+        assert_eq!(
+            RevertReason::decode(&data),
+            Some(RevertReason::Panic(0x11.into()))
+        );
+    }
+
+    #[test]
+    fn decode_rejects_malformed_payloads() {
+        assert_eq!(RevertReason::decode(&[]), None);
+        assert_eq!(RevertReason::decode(&[0xde, 0xad, 0xbe, 0xef]), None);
+        // Custom error selector
+        assert_eq!(
+            RevertReason::decode(&hex::decode("12345678").unwrap()),
+            None
+        );
+        // Truncated panic code
+        assert_eq!(
+            RevertReason::decode(&hex::decode("4e487b7100").unwrap()),
+            None
+        );
+        // String length past the end of the data
+        let data = hex::decode(concat!(
+            "08c379a0",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "00000000000000000000000000000000000000000000000000000000000000ff",
+            "4e6f7420656e6f7567682045746865722070726f76696465642e000000000000",
+        ))
+        .unwrap();
+        assert_eq!(RevertReason::decode(&data), None);
+    }
+
+    #[test]
+    fn revert_keeps_output_without_refund() {
+        let mut gas = Gas::new(100_000);
+        gas.charge(30_000).unwrap();
+        gas.record_refund(4_800);
+        let result =
+            ExecutionResult::new(ExecutionStatus::Revert, Bytes::from_static(&[1, 2]), gas, 1);
+        assert!(result.is_revert());
+        assert_eq!(result.returndata.as_ref(), &[1, 2]);
+        assert_eq!(result.gas_refunded, 0);
+        assert_eq!(result.gas_used, 30_000);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::execution_result::ExecutionError;
+use primitive_types::U256;
+
+/// Tracks the gas budget of an execution.
+#[derive(Debug, Clone, Copy)]
+pub struct Gas {
+    limit: u64,
+    used: u64,
+    /// Refund accumulated by SSTORE. Can dip below zero within a call before being paid out.
+    refunded: i64,
+}
+
+impl Gas {
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            used: 0,
+            refunded: 0,
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.limit - self.used
+    }
+
+    /// Deducts `cost` from the remaining gas. Running out consumes everything that was left.
+    pub fn charge(&mut self, cost: u64) -> Result<(), ExecutionError> {
+        if cost > self.remaining() {
+            self.used = self.limit;
+            return Err(ExecutionError::OutOfGas);
+        }
+        self.used += cost;
+        Ok(())
+    }
+
+    pub fn refunded(&self) -> i64 {
+        self.refunded
+    }
+
+    pub fn record_refund(&mut self, refund: i64) {
+        self.refunded += refund;
+    }
+
+    /// Refund paid out at the end of a transaction, capped at a fifth of the gas used (EIP-3529).
+    pub fn final_refund(&self) -> u64 {
+        std::cmp::min(self.refunded.max(0) as u64, self.used / 5)
+    }
+
+    /// Gives back gas that was forwarded to a call but not used by it.
+    pub fn return_gas(&mut self, gas: u64) {
+        self.used = self.used.saturating_sub(gas);
+    }
+
+    /// Burns all remaining gas, as happens on an exceptional halt.
+    pub fn consume_all(&mut self) {
+        self.used = self.limit;
+    }
+}
+
+/// Total fee for a memory of `words` 32-byte words: 3·words + words²/512.
+pub fn memory_cost(words: u64) -> u128 {
+    let words = words as u128;
+    3 * words + words * words / 512
+}
+
+/// Fee for growing memory from `current_words` to `new_words`.
+pub fn memory_expansion_cost(current_words: u64, new_words: u64) -> u128 {
+    if new_words <= current_words {
+        return 0;
+    }
+    memory_cost(new_words) - memory_cost(current_words)
+}
+
+/// Fee for copying `size` bytes: 3 gas per (partial) word.
+pub fn copy_cost(size: usize) -> u64 {
+    3 * size.div_ceil(32) as u64
+}
+
+/// Dynamic part of KECCAK256: 6 gas per (partial) word hashed.
+pub fn keccak256_cost(size: usize) -> u64 {
+    6 * size.div_ceil(32) as u64
+}
+
+/// Dynamic part of CREATE and CREATE2: 2 gas per (partial) word of init code (EIP-3860).
+pub fn initcode_cost(size: usize) -> u64 {
+    2 * size.div_ceil(32) as u64
+}
+
+/// Charged to a creation for every byte of code it deploys.
+pub fn code_deposit_cost(size: usize) -> u64 {
+    200 * size as u64
+}
+
+/// Dynamic part of LOG0–LOG4: 8 gas per byte of data.
+pub fn log_cost(size: usize) -> u64 {
+    8 * size as u64
+}
+
+/// Dynamic part of EXP: 50 gas per byte of the exponent.
+pub fn exp_cost(exponent: U256) -> u64 {
+    50 * exponent.bits().div_ceil(8) as u64
+}
+
+/// Static cost of the opcodes that access accounts or slots, which assumes they are warm.
+pub const WARM_ACCESS_COST: u64 = 100;
+/// Cost of the first access to an account in a transaction (EIP-2929).
+pub const COLD_ACCOUNT_ACCESS_COST: u64 = 2600;
+/// Cost of the first access to a storage slot in a transaction (EIP-2929).
+pub const COLD_SLOAD_COST: u64 = 2100;
+
+/// EIP-2200 stipend: SSTORE fails when no more than this is left.
+pub const SSTORE_SENTRY: u64 = 2300;
+
+/// Cost and refund of an SSTORE to a warm slot, following EIP-2200 with the EIP-2929 and
+/// EIP-3529 repricing. `original` is the value before the transaction, `current` the value
+/// before this write.
+pub fn sstore_cost(original: U256, current: U256, new: U256) -> (u64, i64) {
+    const WARM_READ: u64 = WARM_ACCESS_COST;
+    const SET: u64 = 20_000;
+    const RESET: u64 = 5_000 - 2_100;
+    const CLEARS_REFUND: i64 = 4_800;
+
+    if current == new {
+        return (WARM_READ, 0);
+    }
+    if original == current {
+        if original.is_zero() {
+            return (SET, 0);
+        }
+        let refund = if new.is_zero() { CLEARS_REFUND } else { 0 };
+        return (RESET, refund);
+    }
+
+    let mut refund = 0;
+    if !original.is_zero() {
+        if current.is_zero() {
+            refund -= CLEARS_REFUND;
+        } else if new.is_zero() {
+            refund += CLEARS_REFUND;
+        }
+    }
+    if original == new {
+        if original.is_zero() {
+            refund += (SET - WARM_READ) as i64;
+        } else {
+            refund += (RESET - WARM_READ) as i64;
+        }
+    }
+    (WARM_READ, refund)
+}
+
+/// Surcharge for a call that transfers value.
+pub const CALL_VALUE_COST: u64 = 9000;
+/// Surcharge for a value transfer that brings an account into existence.
+pub const NEW_ACCOUNT_COST: u64 = 25000;
+/// Free gas given to the callee of a value transfer, on top of what the caller forwards.
+pub const CALL_STIPEND: u64 = 2300;
+
+/// Gas forwarded to a call asking for `requested`: capped at all but one 64th of what is
+/// left (EIP-150).
+pub fn call_gas(requested: U256, remaining: u64) -> u64 {
+    let available = remaining - remaining / 64;
+    match u64::try_from(requested) {
+        Ok(requested) => requested.min(available),
+        Err(_) => available,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn charge_within_limit() {
+        let mut gas = Gas::new(10);
+        gas.charge(3).unwrap();
+        gas.charge(7).unwrap();
+        assert_eq!(gas.used(), 10);
+        assert_eq!(gas.remaining(), 0);
+    }
+
+    #[test]
+    fn charge_over_limit_uses_everything() {
+        let mut gas = Gas::new(10);
+        gas.charge(3).unwrap();
+        assert!(matches!(gas.charge(8), Err(ExecutionError::OutOfGas)));
+        assert_eq!(gas.used(), 10);
+        assert_eq!(gas.remaining(), 0);
+    }
+
+    #[test]
+    fn test_memory_cost() {
+        assert_eq!(memory_cost(0), 0);
+        assert_eq!(memory_cost(1), 3);
+        assert_eq!(memory_cost(32), 98);
+        assert_eq!(memory_cost(1024), 5120);
+    }
+
+    #[test]
+    fn test_memory_expansion_cost() {
+        assert_eq!(memory_expansion_cost(0, 1), 3);
+        assert_eq!(memory_expansion_cost(1, 1), 0);
+        assert_eq!(memory_expansion_cost(2, 1), 0);
+        assert_eq!(memory_expansion_cost(32, 1024), 5120 - 98);
+    }
+
+    #[test]
+    fn test_copy_cost() {
+        assert_eq!(copy_cost(0), 0);
+        assert_eq!(copy_cost(1), 3);
+        assert_eq!(copy_cost(32), 3);
+        assert_eq!(copy_cost(33), 6);
+    }
+
+    #[test]
+    fn test_keccak256_cost() {
+        assert_eq!(keccak256_cost(0), 0);
+        assert_eq!(keccak256_cost(32), 6);
+        assert_eq!(keccak256_cost(33), 12);
+    }
+
+    #[test]
+    fn test_exp_cost() {
+        assert_eq!(exp_cost(U256::zero()), 0);
+        assert_eq!(exp_cost(0xff.into()), 50);
+        assert_eq!(exp_cost(0x100.into()), 100);
+        assert_eq!(exp_cost(U256::MAX), 50 * 32);
+    }
+
+    #[test]
+    fn final_refund_is_capped() {
+        let mut gas = Gas::new(100_000);
+        gas.charge(20_000).unwrap();
+        gas.record_refund(19_900);
+        assert_eq!(gas.final_refund(), 4_000);
+
+        gas.record_refund(-19_000);
+        assert_eq!(gas.final_refund(), 900);
+
+        gas.record_refund(-1_000);
+        assert_eq!(gas.final_refund(), 0);
+    }
+
+    #[test]
+    fn test_sstore_cost() {
+        let zero = U256::zero();
+        let one = U256::one();
+        let two = U256::from(2);
+
+        // No-op
+        assert_eq!(sstore_cost(zero, zero, zero), (100, 0));
+        // Fresh slot: set, then reset back
+        assert_eq!(sstore_cost(zero, zero, one), (20_000, 0));
+        assert_eq!(sstore_cost(zero, one, zero), (100, 19_900));
+        assert_eq!(sstore_cost(zero, one, two), (100, 0));
+        // Existing slot: update, clear and restore
+        assert_eq!(sstore_cost(one, one, two), (2_900, 0));
+        assert_eq!(sstore_cost(one, one, zero), (2_900, 4_800));
+        assert_eq!(sstore_cost(one, zero, one), (100, -4_800 + 2_800));
+        assert_eq!(sstore_cost(one, two, zero), (100, 4_800));
+        assert_eq!(sstore_cost(one, two, one), (100, 2_800));
+    }
+
+    #[test]
+    fn call_gas_keeps_a_64th() {
+        assert_eq!(call_gas(U256::MAX, 6400), 6300);
+        assert_eq!(call_gas(1000.into(), 6400), 1000);
+        assert_eq!(call_gas(U256::MAX, 63), 63);
+    }
+}
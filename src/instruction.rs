@@ -1,62 +1,111 @@
 use bytes::Bytes;
-use lazy_static::lazy_static;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 
 use crate::execution_context::ExecutionContext;
 use crate::opcodes::OpcodeExecutor;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Instruction {
-    pub opcode: usize,
+    pub opcode: u8,
     pub name: String,
-    pub executor: Box<dyn OpcodeExecutor>,
+    /// Static gas charged before the instruction executes.
+    pub gas_cost: u64,
+    pub executor: Arc<dyn OpcodeExecutor>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum InstructionError {
     InvalidCodeOffset { code: Bytes, pc: usize },
-    OpcodeNotFound(usize),
+    OpcodeNotFound(u8),
 }
 
-// Note to self: Rust requires the use of a mutex or similar mechanism for mutable statics due to thread safety concerns.
-// Mutex and lazy_static approach is one way to handle this
-lazy_static! {
-    static ref INSTRUCTIONS: Mutex<Vec<Arc<Instruction>>> = Mutex::new(vec![]);
-    pub static ref INSTRUCTIONS_BY_OPCODE: Mutex<HashMap<usize, Arc<Instruction>>> =
-        Mutex::new(HashMap::new());
+/// Jump table mapping every opcode to its instruction, if one is registered.
+/// Each VM owns its table, so tables can be cloned and customised per run.
+#[derive(Debug, Clone)]
+pub struct InstructionTable {
+    instructions: [Option<Instruction>; 256],
 }
-impl Instruction {
-    pub fn register_instruction(opcode: usize, name: String, executor: Box<dyn OpcodeExecutor>) {
-        let instruction = Arc::new(Instruction {
+
+impl Default for InstructionTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InstructionTable {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self {
+            instructions: std::array::from_fn(|_| None),
+        }
+    }
+
+    pub fn register_instruction(
+        &mut self,
+        opcode: u8,
+        name: String,
+        gas_cost: u64,
+        executor: Box<dyn OpcodeExecutor>,
+    ) {
+        self.instructions[opcode as usize] = Some(Instruction {
             opcode,
-            name: name.clone(),
-            executor,
+            name,
+            gas_cost,
+            executor: Arc::from(executor),
         });
+    }
 
-        {
-            let mut instructions = INSTRUCTIONS.lock().unwrap();
-            instructions.push(instruction.clone());
+    pub fn remove_instruction(&mut self, opcode: u8) -> Option<Instruction> {
+        self.instructions[opcode as usize].take()
+    }
 
-            let mut instructions_by_opcode = INSTRUCTIONS_BY_OPCODE.lock().unwrap();
-            instructions_by_opcode.insert(opcode, instruction);
-        }
+    pub fn get(&self, opcode: u8) -> Option<&Instruction> {
+        self.instructions[opcode as usize].as_ref()
     }
 
     pub fn decode_opcode(
+        &self,
         context: &mut ExecutionContext,
-    ) -> Result<Arc<Instruction>, InstructionError> {
-        let opcode = context.read_code(1);
-
-        let instructions_by_opcode = INSTRUCTIONS_BY_OPCODE.lock().unwrap();
+    ) -> Result<&Instruction, InstructionError> {
+        let opcode = context.read_code(1).byte(0);
 
         if context.pc > context.code.len() {
-            Ok(instructions_by_opcode.get(&0x00).cloned().unwrap()) // STOP if pc goes over code length
+            // STOP if pc goes over code length
+            self.get(0x00).ok_or(InstructionError::OpcodeNotFound(0x00))
         } else {
-            match instructions_by_opcode.get(&opcode) {
-                Some(instruction) => Ok(instruction.to_owned()),
-                None => Err(InstructionError::OpcodeNotFound(opcode)),
-            }
+            self.get(opcode)
+                .ok_or(InstructionError::OpcodeNotFound(opcode))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opcodes::Opcodes;
+
+    #[test]
+    fn new_table_is_empty() {
+        let table = InstructionTable::new();
+        assert!((0..=255).all(|opcode| table.get(opcode).is_none()));
+    }
+
+    #[test]
+    fn register_and_get() {
+        let mut table = InstructionTable::new();
+        table.register_instruction(0x01, "ADD".to_string(), 3, Box::new(Opcodes::ADD));
+        assert_eq!(table.get(0x01).unwrap().name, "ADD");
+        assert_eq!(table.get(0x01).unwrap().gas_cost, 3);
+        assert!(table.get(0x02).is_none());
+    }
+
+    #[test]
+    fn cloned_tables_are_independent() {
+        let table = Opcodes::instruction_table();
+        let mut custom = table.clone();
+        custom.remove_instruction(0x01);
+
+        assert!(custom.get(0x01).is_none());
+        assert_eq!(table.get(0x01).unwrap().name, "ADD");
+    }
+}
@@ -1,6 +1,21 @@
+pub mod account;
+pub mod arithmetic;
+pub mod call;
 pub mod calldata;
+pub mod database;
+pub mod env;
+pub mod evm;
 pub mod execution_context;
+pub mod execution_result;
+pub mod gas;
 pub mod instruction;
+pub mod keccak;
+pub mod log;
 pub mod memory;
 pub mod opcodes;
 pub mod stack;
+pub mod state;
+pub mod storage;
+
+/// 20-byte account address.
+pub type Address = primitive_types::H160;
@@ -0,0 +1,12 @@
+use bytes::Bytes;
+use primitive_types::H256;
+
+use crate::Address;
+
+/// An event emitted by one of the LOG0–LOG4 opcodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Log {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Bytes,
+}
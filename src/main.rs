@@ -1,32 +1,443 @@
 use bytes::Bytes;
 use hex::{self};
+use primitive_types::{H256, U256};
+use serde_json::{Map, Value};
 use smol_evm_rs::{
-    execution_context::ExecutionContext, instruction::Instruction, opcodes::Opcodes,
+    account::Account,
+    database::InMemoryDb,
+    env::{AccessListItem, BlockEnv, TxEnv},
+    evm::Evm,
+    Address,
 };
-use std::env::args;
+use std::{env::args, fs, process::exit};
+
+const USAGE: &str = "Usage: smol-evm-rs <BYTECODE> [OPTIONS]
+
+Options:
+  --calldata <HEX>         Transaction data
+  --env <FILE>             JSON file with \"block\" and \"tx\" objects, see README
+  --address <ADDRESS>      Account the code runs as
+  --balance <NUMBER>       Balance of that account
+  --caller <ADDRESS>       Transaction sender
+  --value <NUMBER>         Value sent with the transaction
+  --gas-limit <NUMBER>     Transaction gas limit
+  --gas-price <NUMBER>     Transaction gas price
+  --chain-id <NUMBER>      Chain id
+  --number <NUMBER>        Block number
+  --timestamp <NUMBER>     Block timestamp
+  --coinbase <ADDRESS>     Block beneficiary
+  --block-gas-limit <NUMBER>
+  --basefee <NUMBER>
+  --prevrandao <HEX>
+  --blob-basefee <NUMBER>
+
+Numbers are decimal or 0x-prefixed hex. Flags override values from --env.";
+
+#[derive(Debug, Default)]
+struct Cli {
+    code: Bytes,
+    address: Address,
+    balance: U256,
+    block_env: BlockEnv,
+    tx_env: TxEnv,
+}
+
+impl Cli {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut cli = Cli::default();
+        let mut code = None;
+        // Flags are applied after the env file so they win regardless of their position
+        let mut flags = Vec::new();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                code = Some(decode_hex(&arg)?);
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} expects a value", arg))?;
+            if arg == "--env" {
+                let json = fs::read_to_string(&value)
+                    .map_err(|e| format!("Cannot read {}: {}", value, e))?;
+                cli.apply_env_file(&json)?;
+            } else {
+                flags.push((arg, value));
+            }
+        }
+        for (flag, value) in flags {
+            cli.apply_flag(&flag, &value)?;
+        }
+        cli.code = code.ok_or("No bytecode passed")?;
+        Ok(cli)
+    }
+
+    fn apply_flag(&mut self, flag: &str, value: &str) -> Result<(), String> {
+        match flag {
+            "--address" => self.address = parse_address(value)?,
+            "--balance" => self.balance = parse_number(value)?,
+            "--calldata" => self.set_tx_field("data", value)?,
+            "--caller" | "--value" | "--gas-limit" | "--gas-price" | "--chain-id" => {
+                self.set_tx_field(&field_name(flag), value)?
+            }
+            "--block-gas-limit" => self.set_block_field("gas_limit", value)?,
+            "--number" | "--timestamp" | "--coinbase" | "--basefee" | "--prevrandao"
+            | "--blob-basefee" => self.set_block_field(&field_name(flag), value)?,
+            _ => return Err(format!("Unknown option {}", flag)),
+        }
+        Ok(())
+    }
+
+    fn set_tx_field(&mut self, field: &str, value: &str) -> Result<(), String> {
+        let tx = &mut self.tx_env;
+        match field {
+            "caller" => tx.caller = parse_address(value)?,
+            "gas_price" => tx.gas_price = parse_number(value)?,
+            "gas_limit" => tx.gas_limit = parse_u64(value)?,
+            "value" => tx.value = parse_number(value)?,
+            "data" => tx.data = decode_hex(value)?,
+            "chain_id" => tx.chain_id = parse_u64(value)?,
+            _ => return Err(format!("Unknown tx field {:?}", field)),
+        }
+        Ok(())
+    }
+
+    fn set_block_field(&mut self, field: &str, value: &str) -> Result<(), String> {
+        let block = &mut self.block_env;
+        match field {
+            "number" => block.number = parse_number(value)?,
+            "coinbase" => block.coinbase = parse_address(value)?,
+            "timestamp" => block.timestamp = parse_number(value)?,
+            "gas_limit" => block.gas_limit = parse_number(value)?,
+            "basefee" => block.basefee = parse_number(value)?,
+            "prevrandao" => block.prevrandao = parse_h256(value)?,
+            "blob_basefee" => block.blob_basefee = parse_number(value)?,
+            _ => return Err(format!("Unknown block field {:?}", field)),
+        }
+        Ok(())
+    }
+
+    /// Applies a JSON environment of the form `{"address": .., "balance": .., "block": {..},
+    /// "tx": {..}}`, where the nested keys are the `BlockEnv` and `TxEnv` field names.
+    fn apply_env_file(&mut self, json: &str) -> Result<(), String> {
+        let env: Value =
+            serde_json::from_str(json).map_err(|e| format!("Invalid env file: {}", e))?;
+        for (key, value) in as_object(&env, "env")? {
+            match key.as_str() {
+                "address" => self.address = parse_address(&json_scalar(value)?)?,
+                "balance" => self.balance = parse_number(&json_scalar(value)?)?,
+                "block" => {
+                    for (field, value) in as_object(value, "block")? {
+                        self.set_block_field(field, &json_scalar(value)?)?;
+                    }
+                }
+                "tx" => {
+                    for (field, value) in as_object(value, "tx")? {
+                        if field == "access_list" {
+                            self.tx_env.access_list = parse_access_list(value)?;
+                        } else {
+                            self.set_tx_field(field, &json_scalar(value)?)?;
+                        }
+                    }
+                }
+                _ => return Err(format!("Unknown env field {:?}", key)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `--gas-price` -> `gas_price`
+fn field_name(flag: &str) -> String {
+    flag.trim_start_matches("--").replace('-', "_")
+}
+
+fn as_object<'a>(value: &'a Value, name: &str) -> Result<&'a Map<String, Value>, String> {
+    value
+        .as_object()
+        .ok_or_else(|| format!("{} must be a JSON object", name))
+}
+
+/// Parses `[{"address": .., "storage_keys": [..]}, ..]`.
+fn parse_access_list(value: &Value) -> Result<Vec<AccessListItem>, String> {
+    let items = value.as_array().ok_or("access_list must be a JSON array")?;
+    items
+        .iter()
+        .map(|item| {
+            let mut entry = AccessListItem::default();
+            for (key, value) in as_object(item, "access_list item")? {
+                match key.as_str() {
+                    "address" => entry.address = parse_address(&json_scalar(value)?)?,
+                    "storage_keys" => {
+                        let keys = value
+                            .as_array()
+                            .ok_or("storage_keys must be a JSON array")?;
+                        entry.storage_keys = keys
+                            .iter()
+                            .map(|key| parse_number(&json_scalar(key)?))
+                            .collect::<Result<_, _>>()?;
+                    }
+                    _ => return Err(format!("Unknown access_list field {:?}", key)),
+                }
+            }
+            Ok(entry)
+        })
+        .collect()
+}
+
+/// Accepts JSON strings and unsigned integers, returning them in the form the flags take.
+fn json_scalar(value: &Value) -> Result<String, String> {
+    match value {
+        Value::Number(n) if n.is_u64() => Ok(n.to_string()),
+        Value::String(s) => Ok(s.clone()),
+        _ => Err(format!(
+            "Expected a string or unsigned integer, got {}",
+            value
+        )),
+    }
+}
+
+/// Parses a decimal or `0x`-prefixed hex number.
+fn parse_number(value: &str) -> Result<U256, String> {
+    let number = match value.strip_prefix("0x") {
+        Some(hex) => U256::from_str_radix(hex, 16).ok(),
+        None => U256::from_dec_str(value).ok(),
+    };
+    number.ok_or_else(|| format!("Invalid number {:?}", value))
+}
+
+fn parse_u64(value: &str) -> Result<u64, String> {
+    let number = parse_number(value)?;
+    u64::try_from(number).map_err(|_| format!("{} does not fit in 64 bits", number))
+}
+
+fn parse_address(value: &str) -> Result<Address, String> {
+    let bytes = decode_hex(value)?;
+    if bytes.len() != Address::len_bytes() {
+        return Err(format!("Invalid address {:?}: expected 20 bytes", value));
+    }
+    Ok(Address::from_slice(&bytes))
+}
+
+fn parse_h256(value: &str) -> Result<H256, String> {
+    let bytes = decode_hex(value)?;
+    if bytes.len() > 32 {
+        return Err(format!("Invalid word {:?}: longer than 32 bytes", value));
+    }
+    // Shorter values are left-padded, like a number
+    let mut word = H256::zero();
+    word.as_bytes_mut()[32 - bytes.len()..].copy_from_slice(&bytes);
+    Ok(word)
+}
+
+/// Decodes a hex string, with or without a `0x` prefix.
+fn decode_hex(value: &str) -> Result<Bytes, String> {
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    hex::decode(value)
+        .map(Bytes::from)
+        .map_err(|e| format!("Invalid hex string {:?}: {}", value, e))
+}
 
 fn main() {
-    let max_steps = 1000; // Todo: Should replace with gas
-    Opcodes::register_instructions();
-
-    let hex_str = args().nth(1).expect("No bytecode passed");
-    let byte_array = hex::decode(&hex_str).expect("Invalid hex string");
-    let code = Bytes::from(byte_array);
-    let mut context = ExecutionContext::new(code);
-    let mut steps = 1;
-    while !context.stopped {
-        let pc_before = context.pc.clone();
-        let instruction = Instruction::decode_opcode(&mut context).unwrap();
-        instruction.executor.execute(&mut context);
-        steps += 1;
-        if steps > max_steps {
-            panic!("Error: Out of gas"); // Todo: Handle gracefully
-        }
-        println!("{:?} @ pc={}", instruction.name, pc_before);
-        println!("Stack: {:?}", context.stack.stack);
-        println!("Memory: {:?}", context.memory.memory);
-        println!("---------");
-    }
-
-    println!("{}", format!("Output : 0x{:x}", context.returndata));
+    if args().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+    let cli = match Cli::parse(args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            exit(1);
+        }
+    };
+    // The code runs as an account holding it, so EXTCODE* on ADDRESS agree with CODE*
+    let mut db = InMemoryDb::new();
+    db.insert_account(
+        cli.address,
+        Account::new(cli.balance).with_code(cli.code.clone()),
+    );
+    let mut evm = Evm::builder()
+        .code(cli.code)
+        .address(cli.address)
+        .database(db)
+        .tx_env(cli.tx_env)
+        .block_env(cli.block_env)
+        .build();
+
+    while !evm.is_stopped() {
+        let pc_before = evm.current_context().pc;
+        let depth_before = evm.depth();
+        if let Ok(Some(instruction)) = evm.step() {
+            println!(
+                "{:?} @ pc={} depth={}",
+                instruction.name, pc_before, depth_before
+            );
+            // After a call or return this is the frame execution continues in
+            let context = evm.current_context();
+            println!("Stack: {:?}", context.stack.stack);
+            println!("Memory: {:?}", context.memory.memory);
+            println!("---------");
+        }
+    }
+
+    let result = evm.result();
+    println!("Status: {:?}", result.status);
+    println!("Gas used: {}", result.gas_used);
+    println!("Gas remaining: {}", result.gas_remaining);
+    println!("Output : 0x{:x}", result.returndata);
+    if let Some(reason) = result.revert_reason() {
+        println!("Revert reason: {:?}", reason);
+    }
+
+    if !result.logs.is_empty() {
+        println!("Logs:");
+        for log in &result.logs {
+            println!(
+                "  {:?} topics: {:?} data: 0x{:x}",
+                log.address, log.topics, log.data
+            );
+        }
+    }
+
+    if !result.storage_changes.is_empty() {
+        println!("Storage changes:");
+        for change in &result.storage_changes {
+            println!(
+                "  {:?} [0x{:x}]: 0x{:x} -> 0x{:x}",
+                change.address, change.key, change.original, change.current
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        Cli::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn env(json: &str) -> Result<Cli, String> {
+        let mut cli = Cli::default();
+        cli.apply_env_file(json)?;
+        Ok(cli)
+    }
+
+    #[test]
+    fn parses_code_and_flags() {
+        let cli = parse(&[
+            "--calldata",
+            "0xaabb",
+            "6001",
+            "--caller",
+            "0x00000000000000000000000000000000000000bb",
+            "--value",
+            "0x10",
+            "--gas-limit",
+            "50000",
+            "--number",
+            "7",
+            "--prevrandao",
+            "0x01",
+        ])
+        .unwrap();
+        assert_eq!(cli.code.as_ref(), &[0x60, 0x01]);
+        assert_eq!(cli.tx_env.data.as_ref(), &[0xaa, 0xbb]);
+        assert_eq!(cli.tx_env.caller, Address::from_low_u64_be(0xbb));
+        assert_eq!(cli.tx_env.value, 16.into());
+        assert_eq!(cli.tx_env.gas_limit, 50_000);
+        assert_eq!(cli.block_env.number, 7.into());
+        assert_eq!(cli.block_env.prevrandao, H256::from_low_u64_be(1));
+    }
+
+    #[test]
+    fn flags_override_the_env_file() {
+        let path = std::env::temp_dir().join(format!("smol-evm-env-{}.json", std::process::id()));
+        fs::write(&path, r#"{"tx": {"gas_price": 9, "chain_id": 5}}"#).unwrap();
+        let cli = parse(&["--gas-price", "3", "--env", path.to_str().unwrap(), "00"]);
+        fs::remove_file(&path).unwrap();
+        let cli = cli.unwrap();
+        assert_eq!(cli.tx_env.gas_price, 3.into());
+        assert_eq!(cli.tx_env.chain_id, 5);
+    }
+
+    #[test]
+    fn parses_env_file() {
+        let cli = env(r#"{
+            "address": "0x00000000000000000000000000000000000000aa",
+            "balance": "1000",
+            "block": {"number": 17000000, "timestamp": "0x64b7f3c0", "basefee": 7},
+            "tx": {
+                "caller": "0x00000000000000000000000000000000000000bb",
+                "data": "0x01",
+                "access_list": [{
+                    "address": "0x00000000000000000000000000000000000000cc",
+                    "storage_keys": [1, "0x02"]
+                }]
+            }
+        }"#)
+        .unwrap();
+        assert_eq!(cli.address, Address::from_low_u64_be(0xaa));
+        assert_eq!(cli.balance, 1000.into());
+        assert_eq!(cli.block_env.number, 17_000_000.into());
+        assert_eq!(cli.block_env.timestamp, 0x64b7f3c0.into());
+        assert_eq!(cli.block_env.basefee, 7.into());
+        assert_eq!(cli.tx_env.caller, Address::from_low_u64_be(0xbb));
+        assert_eq!(cli.tx_env.data.as_ref(), &[0x01]);
+        assert_eq!(
+            cli.tx_env.access_list,
+            vec![AccessListItem {
+                address: Address::from_low_u64_be(0xcc),
+                storage_keys: vec![1.into(), 2.into()],
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_flags() {
+        assert!(parse(&["6g"]).unwrap_err().contains("Invalid hex string"));
+        assert!(parse(&[]).unwrap_err().contains("No bytecode"));
+        assert!(parse(&["00", "--value"])
+            .unwrap_err()
+            .contains("--value expects a value"));
+        assert!(parse(&["00", "--colour", "red"])
+            .unwrap_err()
+            .contains("Unknown option --colour"));
+        assert!(parse(&["00", "--caller", "0xbb"])
+            .unwrap_err()
+            .contains("expected 20 bytes"));
+        assert!(parse(&["00", "--value", "12a"])
+            .unwrap_err()
+            .contains("Invalid number"));
+        assert!(parse(&["00", "--gas-limit", "0x10000000000000000"])
+            .unwrap_err()
+            .contains("does not fit in 64 bits"));
+        assert!(parse(&["00", "--prevrandao", &"ff".repeat(33)])
+            .unwrap_err()
+            .contains("longer than 32 bytes"));
+    }
+
+    #[test]
+    fn rejects_malformed_env_files() {
+        assert!(env("{").unwrap_err().contains("Invalid env file"));
+        assert!(env("[]").unwrap_err().contains("env must be a JSON object"));
+        assert!(env(r#"{"gas": 1}"#)
+            .unwrap_err()
+            .contains("Unknown env field"));
+        assert!(env(r#"{"block": 1}"#)
+            .unwrap_err()
+            .contains("block must be a JSON object"));
+        assert!(env(r#"{"tx": {"nonce": 1}}"#)
+            .unwrap_err()
+            .contains("Unknown tx field"));
+        assert!(env(r#"{"block": {"number": -1}}"#)
+            .unwrap_err()
+            .contains("Expected a string or unsigned integer"));
+        assert!(env(r#"{"tx": {"access_list": {}}}"#)
+            .unwrap_err()
+            .contains("access_list must be a JSON array"));
+        assert!(env(r#"{"tx": {"access_list": [{"keys": []}]}}"#)
+            .unwrap_err()
+            .contains("Unknown access_list field"));
+    }
 }
@@ -1,92 +1,85 @@
-use bytes::{BufMut, Bytes, BytesMut};
+use bytes::Bytes;
+use primitive_types::U256;
 
-#[derive(Debug)]
+/// Byte-addressed, word-aligned EVM memory. Reads past the end see zeroes.
+#[derive(Debug, Default)]
 pub struct Memory {
-    pub memory: Vec<usize>,
-}
-
-#[derive(Debug)]
-pub enum MemoryError {
-    InvalidMemoryAccess(usize, usize),
-    InvalidMemoryValue(usize, usize),
-    InvalidOffset(usize),
+    pub memory: Vec<u8>,
 }
 
 impl Memory {
-    const ZERO_WORD: [usize; 16] = [0; 16];
+    const WORD_SIZE: usize = 32;
+
     pub fn new() -> Self {
         Self { memory: Vec::new() }
     }
 
-    pub fn store(&mut self, offset: usize, value: usize) -> Result<(), MemoryError> {
-        if value >= u8::MAX.into() {
-            return Err(MemoryError::InvalidMemoryValue(offset, value));
-        }
-
+    pub fn store(&mut self, offset: usize, value: u8) {
         self.expand_if_needed(offset);
         self.memory[offset] = value;
-        Ok(())
     }
 
-    pub fn load(&mut self, offset: usize) -> usize {
-        if offset > self.memory.len() {
-            return 0;
+    pub fn load(&self, offset: usize) -> u8 {
+        self.memory.get(offset).copied().unwrap_or(0)
+    }
+
+    pub fn store_range(&mut self, offset: usize, data: &[u8]) {
+        if data.is_empty() {
+            return;
         }
-        self.expand_if_needed(offset);
-        self.memory[offset]
+        self.expand_if_needed(offset + data.len() - 1);
+        self.memory[offset..offset + data.len()].copy_from_slice(data);
     }
 
     pub fn load_range(&mut self, offset: usize, length: usize) -> Bytes {
-        self.expand_if_needed(offset);
-        let mut bytes = BytesMut::new();
-        for i in offset..offset + length {
-            let data = self.load(i).to_ne_bytes(); // Convert usize to [u8; 8]
-            for byte in &data {
-                bytes.put_u8(*byte);
-            }
+        if length == 0 {
+            return Bytes::new();
         }
-        bytes.freeze()
+        self.expand_if_needed(offset + length - 1);
+        Bytes::copy_from_slice(&self.memory[offset..offset + length])
     }
 
-    // TODO:
-    // Currently handles only upto 16 bytes
-    // pub fn load_word(&mut self, offset: usize) -> u128 {
-    //     let range = self.load_range(offset, 16); // Ensure loading 16 bytes for a u128
-    //     let mut bytes = [0u8; 16];
-    //     // Copy bytes from 'range' into 'bytes' array
-    //     for (i, &byte) in range.iter().enumerate() {
-    //         bytes[i] = byte;
-    //     }
+    /// Copies `length` bytes of `data`, starting at `data_offset`, into memory at `offset`.
+    /// Whatever lies past the end of `data` is written as zeroes.
+    pub fn store_padded(&mut self, offset: usize, data: &[u8], data_offset: usize, length: usize) {
+        let mut bytes = vec![0u8; length];
+        if data_offset < data.len() {
+            let available = std::cmp::min(length, data.len() - data_offset);
+            bytes[..available].copy_from_slice(&data[data_offset..data_offset + available]);
+        }
+        self.store_range(offset, &bytes);
+    }
 
-    //     // Convert the bytes array into a u128
-    //     u128::from_be_bytes(bytes)
-    // }
+    pub fn store_word(&mut self, offset: usize, value: U256) {
+        let mut bytes = [0u8; 32];
+        value.to_big_endian(&mut bytes);
+        self.store_range(offset, &bytes);
+    }
 
-    pub fn store_word(&mut self, offset: usize, value: usize) {
-        self.expand_if_needed(offset + 16);
+    pub fn load_word(&mut self, offset: usize) -> U256 {
+        U256::from_big_endian(&self.load_range(offset, Self::WORD_SIZE))
+    }
 
-        for i in 0..16 {
-            self.memory[offset + 16 - i] = value & (255_u128.wrapping_shl((i * 8) as u32)) as usize;
+    /// Copies `length` bytes from `src` to `dst`. The ranges may overlap.
+    pub fn copy(&mut self, dst: usize, src: usize, length: usize) {
+        if length == 0 {
+            return;
         }
+        self.expand_if_needed(std::cmp::max(dst, src) + length - 1);
+        self.memory.copy_within(src..src + length, dst);
     }
 
     pub fn active_words(&self) -> usize {
-        match self.memory.len().checked_div(16) {
-            Some(v) => v,
-            None => todo!(),
-        }
+        self.memory.len() / Self::WORD_SIZE
     }
 
-    fn expand_if_needed(&mut self, offset: usize) {
+    /// Grows memory by whole words until `offset` is addressable.
+    pub fn expand_if_needed(&mut self, offset: usize) {
         if offset < self.memory.len() {
             return;
         }
-        let active_words_after = std::cmp::max(self.active_words(), (offset + 1).div_ceil(16));
-        let additional_words = active_words_after.saturating_sub(self.active_words());
-        for _ in 0..additional_words {
-            self.memory.extend_from_slice(&Self::ZERO_WORD);
-        }
-        return;
+        let active_words_after = (offset + 1).div_ceil(Self::WORD_SIZE);
+        self.memory.resize(active_words_after * Self::WORD_SIZE, 0);
     }
 }
 
@@ -105,18 +98,18 @@ mod tests {
         let offset = 0;
         let value = 10;
         let mut memory = Memory::new();
-        let _ = memory.store(offset, value);
-        assert_eq!(memory.memory.len(), 16);
+        memory.store(offset, value);
+        assert_eq!(memory.memory.len(), 32);
 
-        memory.store(2, value).unwrap();
-        assert_eq!(memory.memory.len(), 16);
+        memory.store(2, value);
+        assert_eq!(memory.memory.len(), 32);
 
-        memory.store(15, value).unwrap();
-        assert_eq!(memory.memory.len(), 16);
+        memory.store(31, value);
+        assert_eq!(memory.memory.len(), 32);
 
         // Activate 2nd word
-        memory.store(17, value).unwrap();
-        assert_eq!(memory.memory.len(), 32);
+        memory.store(33, value);
+        assert_eq!(memory.memory.len(), 64);
     }
 
     #[test]
@@ -124,21 +117,21 @@ mod tests {
         let offset = 0;
         let value = 10;
         let mut memory = Memory::new();
-        let _ = memory.store(offset, value);
+        memory.store(offset, value);
         assert_eq!(memory.active_words(), 1);
 
-        memory.store(2, value).unwrap();
+        memory.store(2, value);
         assert_eq!(memory.active_words(), 1);
 
-        memory.store(15, value).unwrap();
+        memory.store(31, value);
         assert_eq!(memory.active_words(), 1);
 
         // Activate 3rd word
-        memory.store(35, value).unwrap();
+        memory.store(67, value);
         assert_eq!(memory.active_words(), 3);
 
         // Activate 5th word
-        memory.store(65, value).unwrap();
+        memory.store(129, value);
         assert_eq!(memory.active_words(), 5);
     }
 
@@ -147,8 +140,82 @@ mod tests {
         let offset = 0;
         let value = 10;
         let mut memory = Memory::new();
-        let _ = memory.store(offset, value);
+        memory.store(offset, value);
         let value = memory.load(offset);
         assert_eq!(value, 10);
+
+        // Past the end reads as zero without expanding
+        assert_eq!(memory.load(100), 0);
+        assert_eq!(memory.active_words(), 1);
+    }
+
+    #[test]
+    fn store_and_load_word() {
+        let mut memory = Memory::new();
+        let value = U256::MAX - 1;
+        memory.store_word(1, value);
+        assert_eq!(memory.active_words(), 2);
+        assert_eq!(memory.load(1), 0xff);
+        assert_eq!(memory.load(32), 0xfe);
+        assert_eq!(memory.load_word(1), value);
+    }
+
+    #[test]
+    fn store_and_load_range() {
+        let mut memory = Memory::new();
+        memory.store_range(30, &[0xaa, 0xbb, 0xcc]);
+        assert_eq!(memory.active_words(), 2);
+        assert_eq!(
+            memory.load_range(29, 5).as_ref(),
+            &[0x00, 0xaa, 0xbb, 0xcc, 0x00]
+        );
+
+        // Empty ranges don't touch memory
+        memory.store_range(1000, &[]);
+        assert!(memory.load_range(1000, 0).is_empty());
+        assert_eq!(memory.active_words(), 2);
+    }
+
+    #[test]
+    fn load_range_expands_memory() {
+        let mut memory = Memory::new();
+        assert_eq!(memory.load_range(0, 33).as_ref(), &[0u8; 33]);
+        assert_eq!(memory.active_words(), 2);
+    }
+
+    #[test]
+    fn copy_overlapping_ranges() {
+        let mut memory = Memory::new();
+        memory.store_range(0, &[1, 2, 3, 4]);
+
+        memory.copy(1, 0, 4);
+        assert_eq!(memory.load_range(0, 6).as_ref(), &[1, 1, 2, 3, 4, 0]);
+
+        memory.copy(0, 2, 4);
+        assert_eq!(memory.load_range(0, 6).as_ref(), &[2, 3, 4, 0, 4, 0]);
+    }
+
+    #[test]
+    fn copy_expands_to_destination() {
+        let mut memory = Memory::new();
+        memory.store(0, 0xaa);
+        memory.copy(40, 0, 1);
+        assert_eq!(memory.active_words(), 2);
+        assert_eq!(memory.load(40), 0xaa);
+    }
+
+    #[test]
+    fn store_padded_past_end_of_data() {
+        let mut memory = Memory::new();
+        memory.store_range(0, &[0xff; 8]);
+
+        memory.store_padded(1, &[1, 2, 3], 1, 4);
+        assert_eq!(
+            memory.load_range(0, 8).as_ref(),
+            &[0xff, 2, 3, 0, 0, 0xff, 0xff, 0xff]
+        );
+
+        memory.store_padded(0, &[1, 2, 3], usize::MAX, 2);
+        assert_eq!(memory.load_range(0, 3).as_ref(), &[0, 0, 3]);
     }
 }
@@ -1,4 +1,17 @@
-use crate::{execution_context::ExecutionContext, instruction::Instruction};
+use crate::{
+    arithmetic,
+    call::{self, CallKind, CallRequest, MAX_CALL_DEPTH, MAX_INITCODE_SIZE},
+    execution_context::ExecutionContext,
+    execution_result::ExecutionError,
+    gas,
+    instruction::InstructionTable,
+    keccak,
+    log::Log,
+    state::State,
+    Address,
+};
+use bytes::Bytes;
+use primitive_types::{H256, U256};
 use std::fmt::Debug;
 
 #[derive(Debug)]
@@ -7,19 +20,77 @@ pub enum Opcodes {
     ADD,
     MUL,
     SUB,
+    DIV,
+    SDIV,
+    MOD,
+    SMOD,
+    ADDMOD,
+    MULMOD,
+    EXP,
+    SIGNEXTEND,
     LT,
     GT,
+    SLT,
+    SGT,
     EQ,
     SHR,
     SHL,
+    SAR,
     ISZERO,
+    AND,
+    OR,
+    XOR,
+    NOT,
+    BYTE,
+    KECCAK256,
+    SLOAD,
+    SSTORE,
+    TLOAD,
+    TSTORE,
+    ADDRESS,
+    BALANCE,
+    ORIGIN,
+    CALLER,
     CALLVALUE,
     CALLDATALOAD,
     CALLDATASIZE,
+    CALLDATACOPY,
+    CODESIZE,
+    CODECOPY,
+    RETURNDATASIZE,
+    RETURNDATACOPY,
+    GASPRICE,
+    EXTCODESIZE,
+    EXTCODECOPY,
+    EXTCODEHASH,
+    BLOCKHASH,
+    COINBASE,
+    TIMESTAMP,
+    NUMBER,
+    PREVRANDAO,
+    GASLIMIT,
+    CHAINID,
+    SELFBALANCE,
+    BASEFEE,
+    BLOBBASEFEE,
+    MLOAD,
+    MSTORE,
     MSTORE8,
+    MCOPY,
     RETURN,
+    CREATE,
+    CALL,
+    CALLCODE,
+    DELEGATECALL,
+    CREATE2,
+    STATICCALL,
+    SELFDESTRUCT,
+    REVERT,
+    INVALID,
+    POP,
     PC,
     MSIZE,
+    GAS,
     PUSH0,
     PUSH1,
     PUSH2,
@@ -94,505 +165,2366 @@ pub enum Opcodes {
     JUMP,
     JUMPI,
     JUMPDEST,
+
+    // LOG Opcodes
+    LOG0,
+    LOG1,
+    LOG2,
+    LOG3,
+    LOG4,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Errors {
-    InvalidJumpDestination(usize),
+    InvalidJumpDestination(U256),
+    ReturnDataOutOfBounds {
+        offset: U256,
+        size: U256,
+    },
+    /// The designated INVALID instruction (0xFE).
+    InvalidInstruction,
+    /// A state-modifying instruction inside a STATICCALL.
+    StateChangeInStaticCall,
+    /// CREATE or CREATE2 with init code over the EIP-3860 limit.
+    InitCodeTooLarge(usize),
+    /// A creation returned code over the EIP-170 limit.
+    CodeTooLarge(usize),
+    /// A creation returned code starting with 0xEF, which EIP-3541 reserves.
+    InvalidCodePrefix,
+}
+
+/// Pops a memory range and `topic_count` topics and appends them as a log entry.
+fn log(
+    context: &mut ExecutionContext,
+    state: &mut State,
+    topic_count: usize,
+) -> Result<(), ExecutionError> {
+    context.ensure_mutable()?;
+    let offset = context.stack.pop()?;
+    let size = context.stack.pop()?;
+    let mut topics = Vec::with_capacity(topic_count);
+    for _ in 0..topic_count {
+        let mut topic = H256::zero();
+        context.stack.pop()?.to_big_endian(topic.as_bytes_mut());
+        topics.push(topic);
+    }
+    let (offset, size) = context.expand_memory(offset, size)?;
+    context.gas.charge(gas::log_cost(size))?;
+    let data = context.memory.load_range(offset, size);
+    state.logs.push(Log {
+        address: context.address,
+        topics,
+        data,
+    });
+    Ok(())
+}
+
+/// Charges the EIP-2929 surcharge for an account accessed for the first time in the
+/// transaction. The warm cost is part of the static gas of the opcodes that call this.
+fn access_account(
+    context: &mut ExecutionContext,
+    state: &mut State,
+    address: Address,
+) -> Result<(), ExecutionError> {
+    if state.warm_address(address) {
+        context
+            .gas
+            .charge(gas::COLD_ACCOUNT_ACCESS_COST - gas::WARM_ACCESS_COST)?;
+    }
+    Ok(())
+}
+
+/// Pops the arguments of one of the CALL opcodes, charges for the call and hands it to the
+/// `Evm` as `context.call`. Calls that can't be made, because the call stack is too deep or
+/// the caller can't afford the value, push 0 without running.
+fn call(
+    context: &mut ExecutionContext,
+    state: &mut State,
+    kind: CallKind,
+) -> Result<(), ExecutionError> {
+    let gas = context.stack.pop()?;
+    let code_address = word_to_address(context.stack.pop()?);
+    let value = match kind {
+        CallKind::Call | CallKind::CallCode => context.stack.pop()?,
+        CallKind::DelegateCall => context.callvalue,
+        CallKind::StaticCall => U256::zero(),
+        CallKind::Create | CallKind::Create2 => unreachable!("creations go through create"),
+    };
+    let args_offset = context.stack.pop()?;
+    let args_size = context.stack.pop()?;
+    let return_offset = context.stack.pop()?;
+    let return_size = context.stack.pop()?;
+
+    let transfers_value = matches!(kind, CallKind::Call | CallKind::CallCode) && !value.is_zero();
+    if kind == CallKind::Call && transfers_value {
+        context.ensure_mutable()?;
+    }
+    access_account(context, state, code_address)?;
+    let (args_offset, args_size) = context.expand_memory(args_offset, args_size)?;
+    let (return_offset, return_size) = context.expand_memory(return_offset, return_size)?;
+    if transfers_value {
+        context.gas.charge(gas::CALL_VALUE_COST)?;
+        let is_new = state
+            .account(code_address)
+            .is_none_or(|account| account.is_empty());
+        if kind == CallKind::Call && is_new {
+            context.gas.charge(gas::NEW_ACCOUNT_COST)?;
+        }
+    }
+    let mut gas_limit = gas::call_gas(gas, context.gas.remaining());
+    context.gas.charge(gas_limit)?;
+    if transfers_value {
+        gas_limit += gas::CALL_STIPEND;
+    }
+
+    context.returndata_buffer = Bytes::new();
+    if context.depth >= MAX_CALL_DEPTH
+        || (transfers_value && state.balance(context.address) < value)
+    {
+        context.gas.return_gas(gas_limit);
+        context.stack.push(U256::zero())?;
+        return Ok(());
+    }
+
+    let (address, caller) = match kind {
+        CallKind::Call | CallKind::StaticCall => (code_address, context.address),
+        CallKind::CallCode => (context.address, context.address),
+        CallKind::DelegateCall => (context.address, context.caller),
+        CallKind::Create | CallKind::Create2 => unreachable!("creations go through create"),
+    };
+    context.call = Some(CallRequest {
+        kind,
+        address,
+        code_address,
+        caller,
+        value,
+        input: context.memory.load_range(args_offset, args_size),
+        gas_limit,
+        is_static: context.is_static || kind == CallKind::StaticCall,
+        return_offset,
+        return_size,
+    });
+    Ok(())
+}
+
+/// Pops the arguments of CREATE or CREATE2, charges for the creation and hands it to the
+/// `Evm` as `context.call`. Creations that can't be attempted push 0 without running.
+fn create(
+    context: &mut ExecutionContext,
+    state: &mut State,
+    kind: CallKind,
+) -> Result<(), ExecutionError> {
+    let value = context.stack.pop()?;
+    let offset = context.stack.pop()?;
+    let size = context.stack.pop()?;
+    let salt = match kind {
+        CallKind::Create2 => context.stack.pop()?,
+        _ => U256::zero(),
+    };
+    context.ensure_mutable()?;
+
+    let (offset, size) = context.expand_memory(offset, size)?;
+    if size > MAX_INITCODE_SIZE {
+        return Err(Errors::InitCodeTooLarge(size).into());
+    }
+    context.gas.charge(gas::initcode_cost(size))?;
+    if kind == CallKind::Create2 {
+        context.gas.charge(gas::keccak256_cost(size))?;
+    }
+    let init_code = context.memory.load_range(offset, size);
+
+    context.returndata_buffer = Bytes::new();
+    let nonce = state
+        .account(context.address)
+        .map_or(0, |account| account.nonce);
+    if context.depth >= MAX_CALL_DEPTH
+        || state.balance(context.address) < value
+        || nonce == u64::MAX
+    {
+        context.stack.push(U256::zero())?;
+        return Ok(());
+    }
+    // The nonce goes up even if the creation fails
+    state.account_mut(context.address).nonce += 1;
+    let address = match kind {
+        CallKind::Create2 => call::create2_address(context.address, salt, &init_code),
+        _ => call::create_address(context.address, nonce),
+    };
+    state.warm_address(address);
+
+    let gas_limit = gas::call_gas(U256::MAX, context.gas.remaining());
+    context.gas.charge(gas_limit)?;
+    // Creating over an existing contract fails, burning the gas it was given
+    let collision = state
+        .account(address)
+        .is_some_and(|account| account.nonce != 0 || !account.code.is_empty());
+    if collision {
+        context.stack.push(U256::zero())?;
+        return Ok(());
+    }
+
+    context.call = Some(CallRequest {
+        kind,
+        address,
+        code_address: address,
+        caller: context.address,
+        value,
+        input: init_code,
+        gas_limit,
+        is_static: false,
+        return_offset: 0,
+        return_size: 0,
+    });
+    Ok(())
+}
+
+/// Left-pads an address to a stack word.
+fn address_to_word(address: Address) -> U256 {
+    U256::from_big_endian(address.as_bytes())
+}
+
+/// The low 20 bytes of a stack word as an address.
+fn word_to_address(word: U256) -> Address {
+    let mut bytes = [0u8; 32];
+    word.to_big_endian(&mut bytes);
+    Address::from_slice(&bytes[12..])
+}
+
+/// Charges for and performs a `*COPY` of `size` bytes of `data` from `offset` into memory at
+/// `dest_offset`, zero-padding past the end of `data`.
+fn copy_to_memory(
+    context: &mut ExecutionContext,
+    data: Bytes,
+    dest_offset: U256,
+    offset: U256,
+    size: U256,
+) -> Result<(), ExecutionError> {
+    let (dest_offset, size) = context.expand_memory(dest_offset, size)?;
+    context.gas.charge(gas::copy_cost(size))?;
+    let offset = usize::try_from(offset).unwrap_or(usize::MAX);
+    context
+        .memory
+        .store_padded(dest_offset, &data, offset, size);
+    Ok(())
 }
 
 impl Opcodes {
-    pub fn register_instructions() {
-        Instruction::register_instruction(0x00, "STOP".to_string(), Box::new(Opcodes::STOP));
-        Instruction::register_instruction(0x01, "ADD".to_string(), Box::new(Opcodes::ADD));
-        Instruction::register_instruction(0x02, "MUL".to_string(), Box::new(Opcodes::MUL));
-        Instruction::register_instruction(0x03, "SUB".to_string(), Box::new(Opcodes::SUB));
+    /// Builds a table with every opcode implemented by `Opcodes`.
+    pub fn instruction_table() -> InstructionTable {
+        let mut table = InstructionTable::new();
+        Self::register_instructions(&mut table);
+        table
+    }
+
+    pub fn register_instructions(table: &mut InstructionTable) {
+        table.register_instruction(0x00, "STOP".to_string(), 0, Box::new(Opcodes::STOP));
+        table.register_instruction(0x01, "ADD".to_string(), 3, Box::new(Opcodes::ADD));
+        table.register_instruction(0x02, "MUL".to_string(), 5, Box::new(Opcodes::MUL));
+        table.register_instruction(0x03, "SUB".to_string(), 3, Box::new(Opcodes::SUB));
+        table.register_instruction(0x04, "DIV".to_string(), 5, Box::new(Opcodes::DIV));
+        table.register_instruction(0x05, "SDIV".to_string(), 5, Box::new(Opcodes::SDIV));
+        table.register_instruction(0x06, "MOD".to_string(), 5, Box::new(Opcodes::MOD));
+        table.register_instruction(0x07, "SMOD".to_string(), 5, Box::new(Opcodes::SMOD));
+        table.register_instruction(0x08, "ADDMOD".to_string(), 8, Box::new(Opcodes::ADDMOD));
+        table.register_instruction(0x09, "MULMOD".to_string(), 8, Box::new(Opcodes::MULMOD));
+        table.register_instruction(0x0A, "EXP".to_string(), 10, Box::new(Opcodes::EXP));
+        table.register_instruction(
+            0x0B,
+            "SIGNEXTEND".to_string(),
+            5,
+            Box::new(Opcodes::SIGNEXTEND),
+        );
+
+        table.register_instruction(0x50, "POP".to_string(), 2, Box::new(Opcodes::POP));
+        table.register_instruction(0x51, "MLOAD".to_string(), 3, Box::new(Opcodes::MLOAD));
+        table.register_instruction(0x52, "MSTORE".to_string(), 3, Box::new(Opcodes::MSTORE));
+        table.register_instruction(0x53, "MSTORE8".to_string(), 3, Box::new(Opcodes::MSTORE8));
+        table.register_instruction(0x5E, "MCOPY".to_string(), 3, Box::new(Opcodes::MCOPY));
 
-        Instruction::register_instruction(0x53, "MSTORE8".to_string(), Box::new(Opcodes::MSTORE8));
-        Instruction::register_instruction(0xf3, "RETURN".to_string(), Box::new(Opcodes::RETURN));
-        Instruction::register_instruction(0x58, "PC".to_string(), Box::new(Opcodes::PC));
-        Instruction::register_instruction(0x59, "MSIZE".to_string(), Box::new(Opcodes::MSIZE));
+        // Storage Instructions
+        table.register_instruction(0x54, "SLOAD".to_string(), 100, Box::new(Opcodes::SLOAD));
+        table.register_instruction(0x55, "SSTORE".to_string(), 0, Box::new(Opcodes::SSTORE));
+        table.register_instruction(0x5C, "TLOAD".to_string(), 100, Box::new(Opcodes::TLOAD));
+        table.register_instruction(0x5D, "TSTORE".to_string(), 100, Box::new(Opcodes::TSTORE));
+        table.register_instruction(0xf3, "RETURN".to_string(), 0, Box::new(Opcodes::RETURN));
+        table.register_instruction(0xfd, "REVERT".to_string(), 0, Box::new(Opcodes::REVERT));
+        table.register_instruction(0xfe, "INVALID".to_string(), 0, Box::new(Opcodes::INVALID));
+        table.register_instruction(0xf0, "CREATE".to_string(), 32000, Box::new(Opcodes::CREATE));
+        table.register_instruction(
+            0xf5,
+            "CREATE2".to_string(),
+            32000,
+            Box::new(Opcodes::CREATE2),
+        );
+        table.register_instruction(0xf1, "CALL".to_string(), 100, Box::new(Opcodes::CALL));
+        table.register_instruction(
+            0xf2,
+            "CALLCODE".to_string(),
+            100,
+            Box::new(Opcodes::CALLCODE),
+        );
+        table.register_instruction(
+            0xf4,
+            "DELEGATECALL".to_string(),
+            100,
+            Box::new(Opcodes::DELEGATECALL),
+        );
+        table.register_instruction(
+            0xfa,
+            "STATICCALL".to_string(),
+            100,
+            Box::new(Opcodes::STATICCALL),
+        );
+        table.register_instruction(
+            0xff,
+            "SELFDESTRUCT".to_string(),
+            5000,
+            Box::new(Opcodes::SELFDESTRUCT),
+        );
+        table.register_instruction(0x58, "PC".to_string(), 2, Box::new(Opcodes::PC));
+        table.register_instruction(0x59, "MSIZE".to_string(), 2, Box::new(Opcodes::MSIZE));
+        table.register_instruction(0x5A, "GAS".to_string(), 2, Box::new(Opcodes::GAS));
 
         // PUSH Instructions
-        Instruction::register_instruction(0x5F, "PUSH0".to_string(), Box::new(Opcodes::PUSH0));
-        Instruction::register_instruction(0x60, "PUSH1".to_string(), Box::new(Opcodes::PUSH1));
-        Instruction::register_instruction(0x61, "PUSH2".to_string(), Box::new(Opcodes::PUSH2));
-        Instruction::register_instruction(0x62, "PUSH3".to_string(), Box::new(Opcodes::PUSH3));
-        Instruction::register_instruction(0x63, "PUSH4".to_string(), Box::new(Opcodes::PUSH4));
-        Instruction::register_instruction(0x64, "PUSH5".to_string(), Box::new(Opcodes::PUSH5));
-        Instruction::register_instruction(0x65, "PUSH6".to_string(), Box::new(Opcodes::PUSH6));
-        Instruction::register_instruction(0x66, "PUSH7".to_string(), Box::new(Opcodes::PUSH7));
-        Instruction::register_instruction(0x67, "PUSH8".to_string(), Box::new(Opcodes::PUSH8));
-        Instruction::register_instruction(0x68, "PUSH9".to_string(), Box::new(Opcodes::PUSH9));
-        Instruction::register_instruction(0x69, "PUSH10".to_string(), Box::new(Opcodes::PUSH10));
-        Instruction::register_instruction(0x6A, "PUSH11".to_string(), Box::new(Opcodes::PUSH11));
-        Instruction::register_instruction(0x6B, "PUSH12".to_string(), Box::new(Opcodes::PUSH12));
-        Instruction::register_instruction(0x6C, "PUSH13".to_string(), Box::new(Opcodes::PUSH13));
-        Instruction::register_instruction(0x6D, "PUSH14".to_string(), Box::new(Opcodes::PUSH14));
-        Instruction::register_instruction(0x6E, "PUSH15".to_string(), Box::new(Opcodes::PUSH15));
-        Instruction::register_instruction(0x6F, "PUSH16".to_string(), Box::new(Opcodes::PUSH16));
-        Instruction::register_instruction(0x70, "PUSH17".to_string(), Box::new(Opcodes::PUSH17));
-        Instruction::register_instruction(0x71, "PUSH18".to_string(), Box::new(Opcodes::PUSH18));
-        Instruction::register_instruction(0x72, "PUSH19".to_string(), Box::new(Opcodes::PUSH19));
-        Instruction::register_instruction(0x73, "PUSH20".to_string(), Box::new(Opcodes::PUSH20));
-        Instruction::register_instruction(0x74, "PUSH21".to_string(), Box::new(Opcodes::PUSH21));
-        Instruction::register_instruction(0x75, "PUSH22".to_string(), Box::new(Opcodes::PUSH22));
-        Instruction::register_instruction(0x76, "PUSH23".to_string(), Box::new(Opcodes::PUSH23));
-        Instruction::register_instruction(0x77, "PUSH24".to_string(), Box::new(Opcodes::PUSH24));
-        Instruction::register_instruction(0x78, "PUSH25".to_string(), Box::new(Opcodes::PUSH25));
-        Instruction::register_instruction(0x79, "PUSH26".to_string(), Box::new(Opcodes::PUSH26));
-        Instruction::register_instruction(0x7A, "PUSH27".to_string(), Box::new(Opcodes::PUSH27));
-        Instruction::register_instruction(0x7B, "PUSH28".to_string(), Box::new(Opcodes::PUSH28));
-        Instruction::register_instruction(0x7C, "PUSH29".to_string(), Box::new(Opcodes::PUSH29));
-        Instruction::register_instruction(0x7D, "PUSH30".to_string(), Box::new(Opcodes::PUSH30));
-        Instruction::register_instruction(0x7E, "PUSH31".to_string(), Box::new(Opcodes::PUSH31));
-        Instruction::register_instruction(0x7F, "PUSH32".to_string(), Box::new(Opcodes::PUSH32));
+        table.register_instruction(0x5F, "PUSH0".to_string(), 2, Box::new(Opcodes::PUSH0));
+        table.register_instruction(0x60, "PUSH1".to_string(), 3, Box::new(Opcodes::PUSH1));
+        table.register_instruction(0x61, "PUSH2".to_string(), 3, Box::new(Opcodes::PUSH2));
+        table.register_instruction(0x62, "PUSH3".to_string(), 3, Box::new(Opcodes::PUSH3));
+        table.register_instruction(0x63, "PUSH4".to_string(), 3, Box::new(Opcodes::PUSH4));
+        table.register_instruction(0x64, "PUSH5".to_string(), 3, Box::new(Opcodes::PUSH5));
+        table.register_instruction(0x65, "PUSH6".to_string(), 3, Box::new(Opcodes::PUSH6));
+        table.register_instruction(0x66, "PUSH7".to_string(), 3, Box::new(Opcodes::PUSH7));
+        table.register_instruction(0x67, "PUSH8".to_string(), 3, Box::new(Opcodes::PUSH8));
+        table.register_instruction(0x68, "PUSH9".to_string(), 3, Box::new(Opcodes::PUSH9));
+        table.register_instruction(0x69, "PUSH10".to_string(), 3, Box::new(Opcodes::PUSH10));
+        table.register_instruction(0x6A, "PUSH11".to_string(), 3, Box::new(Opcodes::PUSH11));
+        table.register_instruction(0x6B, "PUSH12".to_string(), 3, Box::new(Opcodes::PUSH12));
+        table.register_instruction(0x6C, "PUSH13".to_string(), 3, Box::new(Opcodes::PUSH13));
+        table.register_instruction(0x6D, "PUSH14".to_string(), 3, Box::new(Opcodes::PUSH14));
+        table.register_instruction(0x6E, "PUSH15".to_string(), 3, Box::new(Opcodes::PUSH15));
+        table.register_instruction(0x6F, "PUSH16".to_string(), 3, Box::new(Opcodes::PUSH16));
+        table.register_instruction(0x70, "PUSH17".to_string(), 3, Box::new(Opcodes::PUSH17));
+        table.register_instruction(0x71, "PUSH18".to_string(), 3, Box::new(Opcodes::PUSH18));
+        table.register_instruction(0x72, "PUSH19".to_string(), 3, Box::new(Opcodes::PUSH19));
+        table.register_instruction(0x73, "PUSH20".to_string(), 3, Box::new(Opcodes::PUSH20));
+        table.register_instruction(0x74, "PUSH21".to_string(), 3, Box::new(Opcodes::PUSH21));
+        table.register_instruction(0x75, "PUSH22".to_string(), 3, Box::new(Opcodes::PUSH22));
+        table.register_instruction(0x76, "PUSH23".to_string(), 3, Box::new(Opcodes::PUSH23));
+        table.register_instruction(0x77, "PUSH24".to_string(), 3, Box::new(Opcodes::PUSH24));
+        table.register_instruction(0x78, "PUSH25".to_string(), 3, Box::new(Opcodes::PUSH25));
+        table.register_instruction(0x79, "PUSH26".to_string(), 3, Box::new(Opcodes::PUSH26));
+        table.register_instruction(0x7A, "PUSH27".to_string(), 3, Box::new(Opcodes::PUSH27));
+        table.register_instruction(0x7B, "PUSH28".to_string(), 3, Box::new(Opcodes::PUSH28));
+        table.register_instruction(0x7C, "PUSH29".to_string(), 3, Box::new(Opcodes::PUSH29));
+        table.register_instruction(0x7D, "PUSH30".to_string(), 3, Box::new(Opcodes::PUSH30));
+        table.register_instruction(0x7E, "PUSH31".to_string(), 3, Box::new(Opcodes::PUSH31));
+        table.register_instruction(0x7F, "PUSH32".to_string(), 3, Box::new(Opcodes::PUSH32));
 
         // DUP Instructions
 
-        Instruction::register_instruction(0x80, "DUP1".to_string(), Box::new(Opcodes::DUP1));
-        Instruction::register_instruction(0x81, "DUP2".to_string(), Box::new(Opcodes::DUP2));
-        Instruction::register_instruction(0x82, "DUP3".to_string(), Box::new(Opcodes::DUP3));
-        Instruction::register_instruction(0x83, "DUP4".to_string(), Box::new(Opcodes::DUP4));
-        Instruction::register_instruction(0x84, "DUP5".to_string(), Box::new(Opcodes::DUP5));
-        Instruction::register_instruction(0x85, "DUP6".to_string(), Box::new(Opcodes::DUP6));
-        Instruction::register_instruction(0x86, "DUP7".to_string(), Box::new(Opcodes::DUP7));
-        Instruction::register_instruction(0x87, "DUP8".to_string(), Box::new(Opcodes::DUP8));
-        Instruction::register_instruction(0x88, "DUP9".to_string(), Box::new(Opcodes::DUP9));
-        Instruction::register_instruction(0x89, "DUP10".to_string(), Box::new(Opcodes::DUP10));
-        Instruction::register_instruction(0x8A, "DUP11".to_string(), Box::new(Opcodes::DUP11));
-        Instruction::register_instruction(0x8B, "DUP12".to_string(), Box::new(Opcodes::DUP12));
-        Instruction::register_instruction(0x8C, "DUP13".to_string(), Box::new(Opcodes::DUP13));
-        Instruction::register_instruction(0x8D, "DUP14".to_string(), Box::new(Opcodes::DUP14));
-        Instruction::register_instruction(0x8E, "DUP15".to_string(), Box::new(Opcodes::DUP15));
-        Instruction::register_instruction(0x8F, "DUP16".to_string(), Box::new(Opcodes::DUP16));
+        table.register_instruction(0x80, "DUP1".to_string(), 3, Box::new(Opcodes::DUP1));
+        table.register_instruction(0x81, "DUP2".to_string(), 3, Box::new(Opcodes::DUP2));
+        table.register_instruction(0x82, "DUP3".to_string(), 3, Box::new(Opcodes::DUP3));
+        table.register_instruction(0x83, "DUP4".to_string(), 3, Box::new(Opcodes::DUP4));
+        table.register_instruction(0x84, "DUP5".to_string(), 3, Box::new(Opcodes::DUP5));
+        table.register_instruction(0x85, "DUP6".to_string(), 3, Box::new(Opcodes::DUP6));
+        table.register_instruction(0x86, "DUP7".to_string(), 3, Box::new(Opcodes::DUP7));
+        table.register_instruction(0x87, "DUP8".to_string(), 3, Box::new(Opcodes::DUP8));
+        table.register_instruction(0x88, "DUP9".to_string(), 3, Box::new(Opcodes::DUP9));
+        table.register_instruction(0x89, "DUP10".to_string(), 3, Box::new(Opcodes::DUP10));
+        table.register_instruction(0x8A, "DUP11".to_string(), 3, Box::new(Opcodes::DUP11));
+        table.register_instruction(0x8B, "DUP12".to_string(), 3, Box::new(Opcodes::DUP12));
+        table.register_instruction(0x8C, "DUP13".to_string(), 3, Box::new(Opcodes::DUP13));
+        table.register_instruction(0x8D, "DUP14".to_string(), 3, Box::new(Opcodes::DUP14));
+        table.register_instruction(0x8E, "DUP15".to_string(), 3, Box::new(Opcodes::DUP15));
+        table.register_instruction(0x8F, "DUP16".to_string(), 3, Box::new(Opcodes::DUP16));
 
         // SWAP Instructions
-        Instruction::register_instruction(0x90, "SWAP1".to_string(), Box::new(Opcodes::SWAP1));
-        Instruction::register_instruction(0x91, "SWAP2".to_string(), Box::new(Opcodes::SWAP2));
-        Instruction::register_instruction(0x92, "SWAP3".to_string(), Box::new(Opcodes::SWAP3));
-        Instruction::register_instruction(0x93, "SWAP4".to_string(), Box::new(Opcodes::SWAP4));
-        Instruction::register_instruction(0x94, "SWAP5".to_string(), Box::new(Opcodes::SWAP5));
-        Instruction::register_instruction(0x95, "SWAP6".to_string(), Box::new(Opcodes::SWAP6));
-        Instruction::register_instruction(0x96, "SWAP7".to_string(), Box::new(Opcodes::SWAP7));
-        Instruction::register_instruction(0x97, "SWAP8".to_string(), Box::new(Opcodes::SWAP8));
-        Instruction::register_instruction(0x98, "SWAP9".to_string(), Box::new(Opcodes::SWAP9));
-        Instruction::register_instruction(0x99, "SWAP10".to_string(), Box::new(Opcodes::SWAP10));
-        Instruction::register_instruction(0x9A, "SWAP11".to_string(), Box::new(Opcodes::SWAP11));
-        Instruction::register_instruction(0x9B, "SWAP12".to_string(), Box::new(Opcodes::SWAP12));
-        Instruction::register_instruction(0x9C, "SWAP13".to_string(), Box::new(Opcodes::SWAP13));
-        Instruction::register_instruction(0x9D, "SWAP14".to_string(), Box::new(Opcodes::SWAP14));
-        Instruction::register_instruction(0x9E, "SWAP15".to_string(), Box::new(Opcodes::SWAP15));
-        Instruction::register_instruction(0x9F, "SWAP16".to_string(), Box::new(Opcodes::SWAP16));
+        table.register_instruction(0x90, "SWAP1".to_string(), 3, Box::new(Opcodes::SWAP1));
+        table.register_instruction(0x91, "SWAP2".to_string(), 3, Box::new(Opcodes::SWAP2));
+        table.register_instruction(0x92, "SWAP3".to_string(), 3, Box::new(Opcodes::SWAP3));
+        table.register_instruction(0x93, "SWAP4".to_string(), 3, Box::new(Opcodes::SWAP4));
+        table.register_instruction(0x94, "SWAP5".to_string(), 3, Box::new(Opcodes::SWAP5));
+        table.register_instruction(0x95, "SWAP6".to_string(), 3, Box::new(Opcodes::SWAP6));
+        table.register_instruction(0x96, "SWAP7".to_string(), 3, Box::new(Opcodes::SWAP7));
+        table.register_instruction(0x97, "SWAP8".to_string(), 3, Box::new(Opcodes::SWAP8));
+        table.register_instruction(0x98, "SWAP9".to_string(), 3, Box::new(Opcodes::SWAP9));
+        table.register_instruction(0x99, "SWAP10".to_string(), 3, Box::new(Opcodes::SWAP10));
+        table.register_instruction(0x9A, "SWAP11".to_string(), 3, Box::new(Opcodes::SWAP11));
+        table.register_instruction(0x9B, "SWAP12".to_string(), 3, Box::new(Opcodes::SWAP12));
+        table.register_instruction(0x9C, "SWAP13".to_string(), 3, Box::new(Opcodes::SWAP13));
+        table.register_instruction(0x9D, "SWAP14".to_string(), 3, Box::new(Opcodes::SWAP14));
+        table.register_instruction(0x9E, "SWAP15".to_string(), 3, Box::new(Opcodes::SWAP15));
+        table.register_instruction(0x9F, "SWAP16".to_string(), 3, Box::new(Opcodes::SWAP16));
 
         // JUMP Instructions
-        Instruction::register_instruction(0x56, "JUMP".to_string(), Box::new(Opcodes::JUMP));
-        Instruction::register_instruction(0x57, "JUMPI".to_string(), Box::new(Opcodes::JUMPI));
-        Instruction::register_instruction(
-            0x5B,
-            "JUMPDEST".to_string(),
-            Box::new(Opcodes::JUMPDEST),
-        );
+        table.register_instruction(0x56, "JUMP".to_string(), 8, Box::new(Opcodes::JUMP));
+        table.register_instruction(0x57, "JUMPI".to_string(), 10, Box::new(Opcodes::JUMPI));
+        table.register_instruction(0x5B, "JUMPDEST".to_string(), 1, Box::new(Opcodes::JUMPDEST));
+
+        // LOG Instructions
+        table.register_instruction(0xA0, "LOG0".to_string(), 375, Box::new(Opcodes::LOG0));
+        table.register_instruction(0xA1, "LOG1".to_string(), 750, Box::new(Opcodes::LOG1));
+        table.register_instruction(0xA2, "LOG2".to_string(), 1125, Box::new(Opcodes::LOG2));
+        table.register_instruction(0xA3, "LOG3".to_string(), 1500, Box::new(Opcodes::LOG3));
+        table.register_instruction(0xA4, "LOG4".to_string(), 1875, Box::new(Opcodes::LOG4));
 
         // Compare Instructions
-        Instruction::register_instruction(0x10, "LT".to_string(), Box::new(Opcodes::LT));
-        Instruction::register_instruction(0x11, "GT".to_string(), Box::new(Opcodes::GT));
-        Instruction::register_instruction(0x14, "EQ".to_string(), Box::new(Opcodes::EQ));
-        Instruction::register_instruction(0x1B, "SHL".to_string(), Box::new(Opcodes::SHL));
-        Instruction::register_instruction(0x1C, "SHR".to_string(), Box::new(Opcodes::SHR));
-        Instruction::register_instruction(0x15, "ISZERO".to_string(), Box::new(Opcodes::ISZERO));
-
-        Instruction::register_instruction(
+        table.register_instruction(0x10, "LT".to_string(), 3, Box::new(Opcodes::LT));
+        table.register_instruction(0x11, "GT".to_string(), 3, Box::new(Opcodes::GT));
+        table.register_instruction(0x12, "SLT".to_string(), 3, Box::new(Opcodes::SLT));
+        table.register_instruction(0x13, "SGT".to_string(), 3, Box::new(Opcodes::SGT));
+        table.register_instruction(0x14, "EQ".to_string(), 3, Box::new(Opcodes::EQ));
+        table.register_instruction(0x1B, "SHL".to_string(), 3, Box::new(Opcodes::SHL));
+        table.register_instruction(0x1C, "SHR".to_string(), 3, Box::new(Opcodes::SHR));
+        table.register_instruction(0x1D, "SAR".to_string(), 3, Box::new(Opcodes::SAR));
+        table.register_instruction(0x15, "ISZERO".to_string(), 3, Box::new(Opcodes::ISZERO));
+
+        // Bitwise Instructions
+        table.register_instruction(0x16, "AND".to_string(), 3, Box::new(Opcodes::AND));
+        table.register_instruction(0x17, "OR".to_string(), 3, Box::new(Opcodes::OR));
+        table.register_instruction(0x18, "XOR".to_string(), 3, Box::new(Opcodes::XOR));
+        table.register_instruction(0x19, "NOT".to_string(), 3, Box::new(Opcodes::NOT));
+        table.register_instruction(0x1A, "BYTE".to_string(), 3, Box::new(Opcodes::BYTE));
+
+        table.register_instruction(
+            0x20,
+            "KECCAK256".to_string(),
+            30,
+            Box::new(Opcodes::KECCAK256),
+        );
+
+        table.register_instruction(0x30, "ADDRESS".to_string(), 2, Box::new(Opcodes::ADDRESS));
+        table.register_instruction(0x31, "BALANCE".to_string(), 100, Box::new(Opcodes::BALANCE));
+        table.register_instruction(0x32, "ORIGIN".to_string(), 2, Box::new(Opcodes::ORIGIN));
+        table.register_instruction(0x33, "CALLER".to_string(), 2, Box::new(Opcodes::CALLER));
+        table.register_instruction(
             0x34,
             "CALLVALUE".to_string(),
+            2,
             Box::new(Opcodes::CALLVALUE),
         );
-        Instruction::register_instruction(
+        table.register_instruction(
             0x35,
             "CALLDATALOAD".to_string(),
+            3,
             Box::new(Opcodes::CALLDATALOAD),
         );
-        Instruction::register_instruction(
+        table.register_instruction(
             0x36,
             "CALLDATASIZE".to_string(),
+            2,
             Box::new(Opcodes::CALLDATASIZE),
         );
+        table.register_instruction(
+            0x37,
+            "CALLDATACOPY".to_string(),
+            3,
+            Box::new(Opcodes::CALLDATACOPY),
+        );
+        table.register_instruction(0x38, "CODESIZE".to_string(), 2, Box::new(Opcodes::CODESIZE));
+        table.register_instruction(0x39, "CODECOPY".to_string(), 3, Box::new(Opcodes::CODECOPY));
+        table.register_instruction(0x3A, "GASPRICE".to_string(), 2, Box::new(Opcodes::GASPRICE));
+        table.register_instruction(
+            0x3B,
+            "EXTCODESIZE".to_string(),
+            100,
+            Box::new(Opcodes::EXTCODESIZE),
+        );
+        table.register_instruction(
+            0x3C,
+            "EXTCODECOPY".to_string(),
+            100,
+            Box::new(Opcodes::EXTCODECOPY),
+        );
+        table.register_instruction(
+            0x3D,
+            "RETURNDATASIZE".to_string(),
+            2,
+            Box::new(Opcodes::RETURNDATASIZE),
+        );
+        table.register_instruction(
+            0x3E,
+            "RETURNDATACOPY".to_string(),
+            3,
+            Box::new(Opcodes::RETURNDATACOPY),
+        );
+        table.register_instruction(
+            0x3F,
+            "EXTCODEHASH".to_string(),
+            100,
+            Box::new(Opcodes::EXTCODEHASH),
+        );
+
+        table.register_instruction(
+            0x40,
+            "BLOCKHASH".to_string(),
+            20,
+            Box::new(Opcodes::BLOCKHASH),
+        );
+        table.register_instruction(0x41, "COINBASE".to_string(), 2, Box::new(Opcodes::COINBASE));
+        table.register_instruction(
+            0x42,
+            "TIMESTAMP".to_string(),
+            2,
+            Box::new(Opcodes::TIMESTAMP),
+        );
+        table.register_instruction(0x43, "NUMBER".to_string(), 2, Box::new(Opcodes::NUMBER));
+        table.register_instruction(
+            0x44,
+            "PREVRANDAO".to_string(),
+            2,
+            Box::new(Opcodes::PREVRANDAO),
+        );
+        table.register_instruction(0x45, "GASLIMIT".to_string(), 2, Box::new(Opcodes::GASLIMIT));
+        table.register_instruction(0x46, "CHAINID".to_string(), 2, Box::new(Opcodes::CHAINID));
+        table.register_instruction(
+            0x47,
+            "SELFBALANCE".to_string(),
+            5,
+            Box::new(Opcodes::SELFBALANCE),
+        );
+        table.register_instruction(0x48, "BASEFEE".to_string(), 2, Box::new(Opcodes::BASEFEE));
+        table.register_instruction(
+            0x4A,
+            "BLOBBASEFEE".to_string(),
+            2,
+            Box::new(Opcodes::BLOBBASEFEE),
+        );
     }
 }
 pub trait OpcodeExecutor: Send + Sync + Debug {
-    fn execute(&self, context: &mut ExecutionContext);
+    fn execute(
+        &self,
+        context: &mut ExecutionContext,
+        state: &mut State,
+    ) -> Result<(), ExecutionError>;
 }
 
 impl OpcodeExecutor for Opcodes {
-    fn execute(&self, context: &mut ExecutionContext) {
+    fn execute(
+        &self,
+        context: &mut ExecutionContext,
+        state: &mut State,
+    ) -> Result<(), ExecutionError> {
         match self {
             Opcodes::STOP => {
                 context.stop();
             }
 
             Opcodes::ADD => {
-                let value1 = context.stack.pop().unwrap();
-                let value2 = context.stack.pop().unwrap();
-                context.stack.push(value1.wrapping_add(value2)).unwrap();
+                let value1 = context.stack.pop()?;
+                let value2 = context.stack.pop()?;
+                context.stack.push(value1.overflowing_add(value2).0)?;
             }
             Opcodes::MUL => {
-                let value1 = context.stack.pop().unwrap();
-                let value2 = context.stack.pop().unwrap();
-                context.stack.push(value1.wrapping_mul(value2)).unwrap();
+                let value1 = context.stack.pop()?;
+                let value2 = context.stack.pop()?;
+                context.stack.push(value1.overflowing_mul(value2).0)?;
             }
             Opcodes::SUB => {
-                let a = context.stack.pop().unwrap();
-                let b = context.stack.pop().unwrap();
-                context.stack.push(a.checked_sub(b).unwrap()).unwrap();
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
+                context.stack.push(a.overflowing_sub(b).0)?;
+            }
+            Opcodes::DIV => {
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
+                context.stack.push(arithmetic::div(a, b))?;
+            }
+            Opcodes::SDIV => {
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
+                context.stack.push(arithmetic::sdiv(a, b))?;
+            }
+            Opcodes::MOD => {
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
+                context.stack.push(arithmetic::rem(a, b))?;
+            }
+            Opcodes::SMOD => {
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
+                context.stack.push(arithmetic::smod(a, b))?;
+            }
+            Opcodes::ADDMOD => {
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
+                let n = context.stack.pop()?;
+                context.stack.push(arithmetic::addmod(a, b, n))?;
+            }
+            Opcodes::MULMOD => {
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
+                let n = context.stack.pop()?;
+                context.stack.push(arithmetic::mulmod(a, b, n))?;
+            }
+            Opcodes::EXP => {
+                let base = context.stack.pop()?;
+                let exponent = context.stack.pop()?;
+                context.gas.charge(gas::exp_cost(exponent))?;
+                context.stack.push(arithmetic::exp(base, exponent))?;
+            }
+            Opcodes::SIGNEXTEND => {
+                let byte_index = context.stack.pop()?;
+                let value = context.stack.pop()?;
+                context
+                    .stack
+                    .push(arithmetic::signextend(byte_index, value))?;
             }
 
+            Opcodes::POP => {
+                context.stack.pop()?;
+            }
+            Opcodes::MLOAD => {
+                let offset = context.stack.pop()?;
+                let (offset, _) = context.expand_memory(offset, 32.into())?;
+                let value = context.memory.load_word(offset);
+                context.stack.push(value)?;
+            }
+            Opcodes::MSTORE => {
+                let offset = context.stack.pop()?;
+                let value = context.stack.pop()?;
+                let (offset, _) = context.expand_memory(offset, 32.into())?;
+                context.memory.store_word(offset, value);
+            }
             Opcodes::MSTORE8 => {
-                let offset = context.stack.pop().unwrap();
-                let value = context.stack.pop().unwrap() % 128;
-                context.memory.store(offset, value).unwrap();
+                let offset = context.stack.pop()?;
+                let value = context.stack.pop()?.byte(0);
+                let (offset, _) = context.expand_memory(offset, U256::one())?;
+                context.memory.store(offset, value);
+            }
+            Opcodes::MCOPY => {
+                let dst = context.stack.pop()?;
+                let src = context.stack.pop()?;
+                let length = context.stack.pop()?;
+                // Expansion covers whichever of the two ranges reaches further
+                let (src, _) = context.expand_memory(src, length)?;
+                let (dst, length) = context.expand_memory(dst, length)?;
+                context.gas.charge(gas::copy_cost(length))?;
+                context.memory.copy(dst, src, length);
+            }
+            Opcodes::SLOAD => {
+                let key = context.stack.pop()?;
+                if state.warm_slot(context.address, key) {
+                    context
+                        .gas
+                        .charge(gas::COLD_SLOAD_COST - gas::WARM_ACCESS_COST)?;
+                }
+                let value = state.load_storage(context.address, key);
+                context.stack.push(value)?;
+            }
+            Opcodes::SSTORE => {
+                let key = context.stack.pop()?;
+                let value = context.stack.pop()?;
+                context.ensure_mutable()?;
+                if context.gas.remaining() <= gas::SSTORE_SENTRY {
+                    return Err(ExecutionError::OutOfGas);
+                }
+                let current = state.load_storage(context.address, key);
+                let original = state.storage.original(context.address, key);
+                let (mut cost, refund) = gas::sstore_cost(original, current, value);
+                if state.warm_slot(context.address, key) {
+                    cost += gas::COLD_SLOAD_COST;
+                }
+                context.gas.charge(cost)?;
+                context.gas.record_refund(refund);
+                state.store_storage(context.address, key, value);
+            }
+            Opcodes::TLOAD => {
+                let key = context.stack.pop()?;
+                let value = state.transient_storage.load(context.address, key);
+                context.stack.push(value)?;
+            }
+            Opcodes::TSTORE => {
+                let key = context.stack.pop()?;
+                let value = context.stack.pop()?;
+                context.ensure_mutable()?;
+                state.transient_storage.store(context.address, key, value);
             }
             Opcodes::RETURN => {
-                let offset = context.stack.pop().unwrap();
-                let length = context.stack.pop().unwrap();
+                let offset = context.stack.pop()?;
+                let length = context.stack.pop()?;
+                let (offset, length) = context.expand_memory(offset, length)?;
                 context.set_returndata(offset, length);
             }
-            Opcodes::PC => context.stack.push(context.pc).unwrap(),
+            Opcodes::REVERT => {
+                let offset = context.stack.pop()?;
+                let length = context.stack.pop()?;
+                let (offset, length) = context.expand_memory(offset, length)?;
+                context.revert(offset, length);
+            }
+            Opcodes::INVALID => {
+                return Err(Errors::InvalidInstruction.into());
+            }
+            Opcodes::CREATE => create(context, state, CallKind::Create)?,
+            Opcodes::CREATE2 => create(context, state, CallKind::Create2)?,
+            Opcodes::CALL => call(context, state, CallKind::Call)?,
+            Opcodes::CALLCODE => call(context, state, CallKind::CallCode)?,
+            Opcodes::DELEGATECALL => call(context, state, CallKind::DelegateCall)?,
+            Opcodes::STATICCALL => call(context, state, CallKind::StaticCall)?,
+            Opcodes::SELFDESTRUCT => {
+                let beneficiary = word_to_address(context.stack.pop()?);
+                context.ensure_mutable()?;
+                // The static cost doesn't include a warm access
+                if state.warm_address(beneficiary) {
+                    context.gas.charge(gas::COLD_ACCOUNT_ACCESS_COST)?;
+                }
+                let is_new = state
+                    .account(beneficiary)
+                    .is_none_or(|account| account.is_empty());
+                if is_new && !state.balance(context.address).is_zero() {
+                    context.gas.charge(gas::NEW_ACCOUNT_COST)?;
+                }
+                state.selfdestruct(context.address, beneficiary);
+                context.stop();
+            }
+            Opcodes::PC => context.stack.push(context.pc.into())?,
             Opcodes::MSIZE => context
                 .stack
-                .push(16 * (context.memory.active_words()))
-                .unwrap(),
+                .push((32 * context.memory.active_words()).into())?,
+            // Static gas is charged before execution, so this is what's left after GAS itself
+            Opcodes::GAS => context.stack.push(context.gas.remaining().into())?,
             Opcodes::PUSH0 => {
-                context.stack.push(0).unwrap();
+                context.stack.push(U256::zero())?;
             }
             Opcodes::PUSH1 => {
                 let value = context.read_code(1);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH2 => {
                 let value = context.read_code(2);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH3 => {
                 let value = context.read_code(3);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH4 => {
                 let value = context.read_code(4);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH5 => {
                 let value = context.read_code(5);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH6 => {
                 let value = context.read_code(6);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH7 => {
                 let value = context.read_code(7);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH8 => {
                 let value = context.read_code(8);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH9 => {
                 let value = context.read_code(9);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH10 => {
                 let value = context.read_code(10);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH11 => {
                 let value = context.read_code(11);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH12 => {
                 let value = context.read_code(12);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH13 => {
                 let value = context.read_code(13);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH14 => {
                 let value = context.read_code(14);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH15 => {
                 let value = context.read_code(15);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH16 => {
                 let value = context.read_code(16);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH17 => {
                 let value = context.read_code(17);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH18 => {
                 let value = context.read_code(18);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH19 => {
                 let value = context.read_code(19);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH20 => {
                 let value = context.read_code(20);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH21 => {
                 let value = context.read_code(21);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH22 => {
                 let value = context.read_code(22);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH23 => {
                 let value = context.read_code(23);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH24 => {
                 let value = context.read_code(24);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH25 => {
                 let value = context.read_code(25);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH26 => {
                 let value = context.read_code(26);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH27 => {
                 let value = context.read_code(27);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH28 => {
                 let value = context.read_code(28);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH29 => {
                 let value = context.read_code(29);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH30 => {
                 let value = context.read_code(30);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH31 => {
                 let value = context.read_code(31);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
             Opcodes::PUSH32 => {
                 let value = context.read_code(32);
-                context.stack.push(value).unwrap();
+                context.stack.push(value)?;
             }
 
             // DUP Opcodes
             Opcodes::DUP1 => {
-                let value = context.stack.peek(0).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(0)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP2 => {
-                let value = context.stack.peek(1).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(1)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP3 => {
-                let value = context.stack.peek(2).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(2)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP4 => {
-                let value = context.stack.peek(3).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(3)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP5 => {
-                let value = context.stack.peek(4).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(4)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP6 => {
-                let value = context.stack.peek(5).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(5)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP7 => {
-                let value = context.stack.peek(6).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(6)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP8 => {
-                let value = context.stack.peek(7).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(7)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP9 => {
-                let value = context.stack.peek(8).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(8)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP10 => {
-                let value = context.stack.peek(9).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(9)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP11 => {
-                let value = context.stack.peek(10).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(10)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP12 => {
-                let value = context.stack.peek(11).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(11)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP13 => {
-                let value = context.stack.peek(12).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(12)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP14 => {
-                let value = context.stack.peek(13).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(13)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP15 => {
-                let value = context.stack.peek(14).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(14)?;
+                context.stack.push(value)?;
             }
             Opcodes::DUP16 => {
-                let value = context.stack.peek(15).unwrap();
-                context.stack.push(value).unwrap();
+                let value = context.stack.peek(15)?;
+                context.stack.push(value)?;
             }
 
             // SWAP Opcodes
             Opcodes::SWAP1 => {
-                context.stack.swap(1).unwrap();
+                context.stack.swap(1)?;
             }
             Opcodes::SWAP2 => {
-                context.stack.swap(2).unwrap();
+                context.stack.swap(2)?;
             }
             Opcodes::SWAP3 => {
-                context.stack.swap(3).unwrap();
+                context.stack.swap(3)?;
             }
             Opcodes::SWAP4 => {
-                context.stack.swap(4).unwrap();
+                context.stack.swap(4)?;
             }
             Opcodes::SWAP5 => {
-                context.stack.swap(5).unwrap();
+                context.stack.swap(5)?;
             }
             Opcodes::SWAP6 => {
-                context.stack.swap(6).unwrap();
+                context.stack.swap(6)?;
             }
             Opcodes::SWAP7 => {
-                context.stack.swap(7).unwrap();
+                context.stack.swap(7)?;
             }
             Opcodes::SWAP8 => {
-                context.stack.swap(8).unwrap();
+                context.stack.swap(8)?;
             }
             Opcodes::SWAP9 => {
-                context.stack.swap(9).unwrap();
+                context.stack.swap(9)?;
             }
             Opcodes::SWAP10 => {
-                context.stack.swap(10).unwrap();
+                context.stack.swap(10)?;
             }
             Opcodes::SWAP11 => {
-                context.stack.swap(11).unwrap();
+                context.stack.swap(11)?;
             }
             Opcodes::SWAP12 => {
-                context.stack.swap(12).unwrap();
+                context.stack.swap(12)?;
             }
             Opcodes::SWAP13 => {
-                context.stack.swap(13).unwrap();
+                context.stack.swap(13)?;
             }
             Opcodes::SWAP14 => {
-                context.stack.swap(14).unwrap();
+                context.stack.swap(14)?;
             }
             Opcodes::SWAP15 => {
-                context.stack.swap(15).unwrap();
+                context.stack.swap(15)?;
             }
             Opcodes::SWAP16 => {
-                context.stack.swap(16).unwrap();
+                context.stack.swap(16)?;
             }
 
             // JUMP Instructions
             Opcodes::JUMP => {
-                let target_pc = context.stack.pop().unwrap();
-                if context.jumpdests.contains(&target_pc) {
-                    context.set_pc(target_pc);
-                } else {
-                    panic!("Invalid JumpDestination"); //TODO: Handle gracefully
-                }
+                let target_pc = context.stack.pop()?;
+                context.jump(target_pc)?;
             }
             Opcodes::JUMPI => {
-                let target_pc = context.stack.pop().unwrap();
-                let condition = context.stack.pop().unwrap();
-                if condition != 0 {
-                    if context.jumpdests.contains(&target_pc) {
-                        context.set_pc(target_pc);
-                    } else {
-                        panic!("Invalid JumpDestination"); //TODO: Handle gracefully
-                    }
+                let target_pc = context.stack.pop()?;
+                let condition = context.stack.pop()?;
+                if !condition.is_zero() {
+                    context.jump(target_pc)?;
                 }
             }
             Opcodes::JUMPDEST => {}
 
+            // LOG Instructions
+            Opcodes::LOG0 => log(context, state, 0)?,
+            Opcodes::LOG1 => log(context, state, 1)?,
+            Opcodes::LOG2 => log(context, state, 2)?,
+            Opcodes::LOG3 => log(context, state, 3)?,
+            Opcodes::LOG4 => log(context, state, 4)?,
+
             Opcodes::LT => {
-                let a = context.stack.pop().unwrap();
-                let b = context.stack.pop().unwrap();
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
 
                 if a < b {
-                    context.stack.push(1).unwrap();
+                    context.stack.push(U256::one())?;
                 } else {
-                    context.stack.push(0).unwrap();
+                    context.stack.push(U256::zero())?;
                 }
             }
             Opcodes::GT => {
-                let a = context.stack.pop().unwrap();
-                let b = context.stack.pop().unwrap();
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
 
                 if a > b {
-                    context.stack.push(1).unwrap();
+                    context.stack.push(U256::one())?;
+                } else {
+                    context.stack.push(U256::zero())?;
+                }
+            }
+            Opcodes::SLT => {
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
+
+                if arithmetic::slt(a, b) {
+                    context.stack.push(U256::one())?;
                 } else {
-                    context.stack.push(0).unwrap();
+                    context.stack.push(U256::zero())?;
+                }
+            }
+            Opcodes::SGT => {
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
+
+                if arithmetic::sgt(a, b) {
+                    context.stack.push(U256::one())?;
+                } else {
+                    context.stack.push(U256::zero())?;
                 }
             }
             Opcodes::EQ => {
-                let a = context.stack.pop().unwrap();
-                let b = context.stack.pop().unwrap();
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
 
                 if a == b {
-                    context.stack.push(1).unwrap();
+                    context.stack.push(U256::one())?;
                 } else {
-                    context.stack.push(0).unwrap();
+                    context.stack.push(U256::zero())?;
                 }
             }
             Opcodes::ISZERO => {
-                let a = context.stack.pop().unwrap();
+                let a = context.stack.pop()?;
 
-                if a == 0 {
-                    context.stack.push(1).unwrap();
+                if a.is_zero() {
+                    context.stack.push(U256::one())?;
                 } else {
-                    context.stack.push(0).unwrap();
+                    context.stack.push(U256::zero())?;
                 }
             }
             Opcodes::SHL => {
-                let a = context.stack.pop().unwrap();
-                let b = context.stack.pop().unwrap();
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
 
-                context.stack.push(b << a).unwrap();
+                context.stack.push(arithmetic::shl(a, b))?;
             }
             Opcodes::SHR => {
-                let a = context.stack.pop().unwrap();
-                let b = context.stack.pop().unwrap();
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
 
-                context.stack.push(b >> a).unwrap();
+                context.stack.push(arithmetic::shr(a, b))?;
+            }
+            Opcodes::SAR => {
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
+
+                context.stack.push(arithmetic::sar(a, b))?;
+            }
+            Opcodes::AND => {
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
+                context.stack.push(a & b)?;
+            }
+            Opcodes::OR => {
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
+                context.stack.push(a | b)?;
+            }
+            Opcodes::XOR => {
+                let a = context.stack.pop()?;
+                let b = context.stack.pop()?;
+                context.stack.push(a ^ b)?;
+            }
+            Opcodes::NOT => {
+                let a = context.stack.pop()?;
+                context.stack.push(!a)?;
+            }
+            Opcodes::BYTE => {
+                let index = context.stack.pop()?;
+                let value = context.stack.pop()?;
+                context.stack.push(arithmetic::byte(index, value))?;
+            }
+            Opcodes::KECCAK256 => {
+                let offset = context.stack.pop()?;
+                let size = context.stack.pop()?;
+                let (offset, size) = context.expand_memory(offset, size)?;
+                context.gas.charge(gas::keccak256_cost(size))?;
+                let data = context.memory.load_range(offset, size);
+                context
+                    .stack
+                    .push(U256::from_big_endian(&keccak::keccak256(&data)))?;
+            }
+            Opcodes::ADDRESS => {
+                context.stack.push(address_to_word(context.address))?;
+            }
+            Opcodes::BALANCE => {
+                let address = word_to_address(context.stack.pop()?);
+                access_account(context, state, address)?;
+                context.stack.push(state.balance(address))?;
+            }
+            Opcodes::ORIGIN => {
+                context.stack.push(address_to_word(context.tx_env.caller))?;
+            }
+            Opcodes::CALLER => {
+                context.stack.push(address_to_word(context.caller))?;
             }
             Opcodes::CALLVALUE => {
-                context.stack.push(0).unwrap();
+                context.stack.push(context.callvalue)?;
             }
             Opcodes::CALLDATALOAD => {
-                let offset = context.stack.pop().unwrap();
-                let value = context.calldata.read_word(offset) as usize;
-                context.stack.push(value).unwrap();
+                // Offsets beyond usize are past the end of any calldata, so they read as zero.
+                let offset = usize::try_from(context.stack.pop()?).unwrap_or(usize::MAX);
+                let value = context.calldata.read_word(offset);
+                context.stack.push(value)?;
             }
             Opcodes::CALLDATASIZE => {
-                context.stack.push(context.calldata.data.len()).unwrap();
+                context.stack.push(context.calldata.data.len().into())?;
+            }
+            Opcodes::CALLDATACOPY => {
+                let dest_offset = context.stack.pop()?;
+                let offset = context.stack.pop()?;
+                let size = context.stack.pop()?;
+                let data = context.calldata.data.clone();
+                copy_to_memory(context, data, dest_offset, offset, size)?;
+            }
+            Opcodes::CODESIZE => {
+                context.stack.push(context.code.len().into())?;
+            }
+            Opcodes::CODECOPY => {
+                let dest_offset = context.stack.pop()?;
+                let offset = context.stack.pop()?;
+                let size = context.stack.pop()?;
+                let data = context.code.clone();
+                copy_to_memory(context, data, dest_offset, offset, size)?;
+            }
+            Opcodes::RETURNDATASIZE => {
+                context.stack.push(context.returndata_buffer.len().into())?;
+            }
+            Opcodes::RETURNDATACOPY => {
+                let dest_offset = context.stack.pop()?;
+                let offset = context.stack.pop()?;
+                let size = context.stack.pop()?;
+                // Unlike the other copies, reading past the end of the buffer is a fault
+                match offset.checked_add(size) {
+                    Some(end) if end <= context.returndata_buffer.len().into() => {}
+                    _ => return Err(Errors::ReturnDataOutOfBounds { offset, size }.into()),
+                }
+                let data = context.returndata_buffer.clone();
+                copy_to_memory(context, data, dest_offset, offset, size)?;
+            }
+            Opcodes::GASPRICE => {
+                context.stack.push(context.tx_env.gas_price)?;
+            }
+            Opcodes::EXTCODESIZE => {
+                let address = word_to_address(context.stack.pop()?);
+                access_account(context, state, address)?;
+                let size = state.code(address).len();
+                context.stack.push(size.into())?;
+            }
+            Opcodes::EXTCODECOPY => {
+                let address = word_to_address(context.stack.pop()?);
+                let dest_offset = context.stack.pop()?;
+                let offset = context.stack.pop()?;
+                let size = context.stack.pop()?;
+                access_account(context, state, address)?;
+                let data = state.code(address);
+                copy_to_memory(context, data, dest_offset, offset, size)?;
+            }
+            Opcodes::EXTCODEHASH => {
+                let address = word_to_address(context.stack.pop()?);
+                access_account(context, state, address)?;
+                // Accounts that don't exist or are empty hash to zero (EIP-1052)
+                let hash = match state.account(address) {
+                    Some(account) if !account.is_empty() => {
+                        U256::from_big_endian(account.code_hash.as_bytes())
+                    }
+                    _ => U256::zero(),
+                };
+                context.stack.push(hash)?;
+            }
+            Opcodes::BLOCKHASH => {
+                let number = context.stack.pop()?;
+                let current = context.block_env.number;
+                // Only the 256 most recent complete blocks are available
+                let hash = match u64::try_from(number) {
+                    Ok(n) if number < current && current - number <= 256.into() => {
+                        state.block_hash(n)
+                    }
+                    _ => H256::zero(),
+                };
+                context.stack.push(U256::from_big_endian(hash.as_bytes()))?;
+            }
+            Opcodes::COINBASE => {
+                context
+                    .stack
+                    .push(address_to_word(context.block_env.coinbase))?;
+            }
+            Opcodes::TIMESTAMP => {
+                context.stack.push(context.block_env.timestamp)?;
+            }
+            Opcodes::NUMBER => {
+                context.stack.push(context.block_env.number)?;
+            }
+            Opcodes::PREVRANDAO => {
+                let prevrandao = context.block_env.prevrandao;
+                context
+                    .stack
+                    .push(U256::from_big_endian(prevrandao.as_bytes()))?;
+            }
+            Opcodes::GASLIMIT => {
+                context.stack.push(context.block_env.gas_limit)?;
+            }
+            Opcodes::CHAINID => {
+                context.stack.push(context.tx_env.chain_id.into())?;
+            }
+            Opcodes::SELFBALANCE => {
+                context.stack.push(state.balance(context.address))?;
+            }
+            Opcodes::BASEFEE => {
+                context.stack.push(context.block_env.basefee)?;
+            }
+            Opcodes::BLOBBASEFEE => {
+                context.stack.push(context.block_env.blob_basefee)?;
             }
         }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        account::Account,
+        database::InMemoryDb,
+        env::{AccessListItem, BlockEnv, TxEnv},
+        evm::Evm,
+        execution_result::{ExecutionStatus, RevertReason},
+        storage::{Storage, StorageChange},
+        Address,
+    };
+
+    fn run(hex_code: &str) -> Evm {
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode(hex_code).unwrap()))
+            .build();
+        evm.run();
+        evm
+    }
+
+    fn word(hex_value: &str) -> U256 {
+        U256::from_str_radix(hex_value, 16).unwrap()
+    }
+
+    #[test]
+    fn pop_discards_the_top_word() {
+        // PUSH1 0x01 PUSH1 0x02 POP
+        let evm = run("6001600250");
+        let result = evm.result();
+        assert!(result.is_success());
+        assert_eq!(evm.context.stack.stack, vec![U256::one()]);
+        assert_eq!(result.gas_used, 3 + 3 + 2);
+    }
+
+    #[test]
+    fn pop_on_empty_stack_halts() {
+        let evm = run("50");
+        assert!(matches!(
+            evm.result().status,
+            ExecutionStatus::Halt(ExecutionError::Stack(_))
+        ));
+    }
+
+    #[test]
+    fn mstore_then_mload() {
+        // PUSH32 0x0102..20 PUSH1 0x01 MSTORE PUSH1 0x01 MLOAD
+        let value = "0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20";
+        let evm = run(&format!("7f{}600152600151", value));
+        assert!(evm.result().is_success());
+        assert_eq!(evm.context.stack.stack, vec![word(value)]);
+        assert_eq!(evm.context.memory.active_words(), 2);
+        assert_eq!(evm.context.memory.load(1), 0x01);
+        assert_eq!(evm.context.memory.load(32), 0x20);
+    }
+
+    #[test]
+    fn mload_expands_memory() {
+        // PUSH1 0x20 MLOAD MSIZE
+        let evm = run("60205159");
+        assert_eq!(evm.context.stack.stack, vec![U256::zero(), 64.into()]);
+        // 3 + 3 + 2 words of memory + 2
+        assert_eq!(evm.result().gas_used, 3 + 3 + 6 + 2);
+    }
+
+    #[test]
+    fn free_memory_pointer_setup() {
+        // PUSH1 0x80 PUSH1 0x40 MSTORE, as emitted by solc
+        let mut evm = run("6080604052");
+        assert!(evm.result().is_success());
+        assert_eq!(evm.context.memory.load_word(0x40), 0x80.into());
+    }
+
+    #[test]
+    fn mcopy_word() {
+        // Example from EIP-5656: PUSH32 0x0001..1f PUSH1 0x20 MSTORE
+        //                        PUSH1 0x20 PUSH1 0x20 PUSH0 MCOPY
+        let value = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+        let mut evm = run(&format!("7f{}602052602060205f5e", value));
+        assert!(evm.result().is_success());
+        assert_eq!(evm.context.memory.load_word(0), word(value));
+    }
+
+    #[test]
+    fn mcopy_overlapping() {
+        // PUSH1 0x01 PUSH0 MSTORE8 PUSH1 0x02 PUSH1 0x01 MSTORE8
+        // PUSH1 0x08 PUSH0 PUSH1 0x01 MCOPY: copy [0..8) to [1..9)
+        let mut evm = run("60015f53600260015360085f60015e");
+        assert!(evm.result().is_success());
+        assert_eq!(
+            evm.context.memory.load_range(0, 10).as_ref(),
+            &[1, 1, 2, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn mcopy_charges_copy_and_expansion() {
+        // PUSH1 0x20 PUSH1 0x00 PUSH1 0x20 MCOPY: copy a word from 0 to 0x20
+        let evm = run("6020600060205e");
+        let result = evm.result();
+        assert!(result.is_success());
+        // 3 pushes + MCOPY + 1 word copied + 2 words of memory
+        assert_eq!(result.gas_used, 9 + 3 + 3 + 6);
+    }
+
+    #[test]
+    fn mcopy_zero_length_is_free() {
+        // PUSH0 PUSH32 0xff..ff PUSH32 0xff..ff MCOPY
+        let evm = run(&format!("5f7f{0}7f{0}5e", "ff".repeat(32)));
+        let result = evm.result();
+        assert!(matches!(result.status, ExecutionStatus::Success));
+        assert_eq!(result.gas_used, 2 + 3 + 3 + 3);
+    }
+
+    fn run_with_calldata(hex_code: &str, calldata: &str) -> Evm {
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode(hex_code).unwrap()))
+            .calldata(Bytes::from(hex::decode(calldata).unwrap()))
+            .build();
+        evm.run();
+        evm
+    }
+
+    #[test]
+    fn calldatacopy_zero_pads() {
+        // PUSH1 0x06 PUSH1 0x01 PUSH1 0x02 CALLDATACOPY: copy calldata[1..7) to memory[2..8)
+        let mut evm = run_with_calldata("6006600160023700", "aabbccdd");
+        let result = evm.result();
+        assert!(result.is_success());
+        assert_eq!(
+            evm.context.memory.load_range(0, 9).as_ref(),
+            &[0, 0, 0xbb, 0xcc, 0xdd, 0, 0, 0, 0]
+        );
+        // 3 pushes + CALLDATACOPY + 1 word copied + 1 word of memory
+        assert_eq!(result.gas_used, 9 + 3 + 3 + 3);
+    }
+
+    #[test]
+    fn calldatacopy_huge_offset_copies_zeroes() {
+        // PUSH1 0x20 PUSH32 0xff..ff PUSH0 CALLDATACOPY
+        let mut evm = run_with_calldata(&format!("60207f{}5f37", "ff".repeat(32)), "aabbccdd");
+        assert!(evm.result().is_success());
+        assert_eq!(evm.context.memory.load_word(0), U256::zero());
+    }
+
+    #[test]
+    fn codesize_and_codecopy() {
+        // CODESIZE PUSH1 0x03 PUSH1 0x00 PUSH1 0x00 CODECOPY
+        let mut evm = run("3860036000600039");
+        assert!(evm.result().is_success());
+        assert_eq!(evm.context.stack.stack, vec![U256::from(8)]);
+        assert_eq!(
+            evm.context.memory.load_range(0, 4).as_ref(),
+            &[0x38, 0x60, 0x03, 0x00]
+        );
+    }
+
+    #[test]
+    fn returndatasize_starts_empty() {
+        // RETURNDATASIZE
+        let evm = run("3d");
+        assert_eq!(evm.context.stack.stack, vec![U256::zero()]);
+    }
+
+    #[test]
+    fn returndatacopy_past_buffer_faults() {
+        // PUSH1 0x01 PUSH0 PUSH0 RETURNDATACOPY
+        let evm = run("60015f5f3e");
+        assert!(matches!(
+            evm.result().status,
+            ExecutionStatus::Halt(ExecutionError::Opcode(Errors::ReturnDataOutOfBounds { .. }))
+        ));
+
+        // A zero-sized copy at the end of the buffer is fine: PUSH0 PUSH0 PUSH0 RETURNDATACOPY
+        let evm = run("5f5f5f3e");
+        assert!(evm.result().is_success());
+    }
+
+    /// Runs `PUSH32 c PUSH32 b PUSH32 a <opcode>` and returns the result.
+    fn apply(opcode: &str, args: &[U256]) -> U256 {
+        let mut code = String::new();
+        for arg in args.iter().rev() {
+            let mut bytes = [0u8; 32];
+            arg.to_big_endian(&mut bytes);
+            code.push_str(&format!("7f{}", hex::encode(bytes)));
+        }
+        code.push_str(opcode);
+        let mut evm = run(&code);
+        assert!(evm.result().is_success());
+        evm.context.stack.pop().unwrap()
+    }
+
+    #[test]
+    fn arithmetic_opcodes() {
+        let minus = |value: u64| arithmetic::negate(value.into());
+
+        assert_eq!(apply("01", &[U256::MAX, 2.into()]), 1.into());
+        assert_eq!(apply("02", &[U256::MAX, 2.into()]), U256::MAX - 1);
+        assert_eq!(apply("03", &[U256::zero(), 1.into()]), U256::MAX);
+        assert_eq!(apply("04", &[10.into(), 3.into()]), 3.into());
+        assert_eq!(apply("04", &[10.into(), U256::zero()]), U256::zero());
+        assert_eq!(apply("05", &[minus(10), 3.into()]), minus(3));
+        assert_eq!(
+            apply("05", &[arithmetic::MIN_NEGATIVE, minus(1)]),
+            arithmetic::MIN_NEGATIVE
+        );
+        assert_eq!(apply("06", &[10.into(), 3.into()]), 1.into());
+        assert_eq!(apply("07", &[minus(8), minus(3)]), minus(2));
+        assert_eq!(apply("08", &[U256::MAX, 2.into(), 2.into()]), 1.into());
+        assert_eq!(apply("09", &[U256::MAX, U256::MAX, 12.into()]), 9.into());
+        assert_eq!(apply("0a", &[2.into(), 2.into()]), 4.into());
+        assert_eq!(apply("0b", &[U256::zero(), 0xff.into()]), U256::MAX);
+    }
+
+    #[test]
+    fn exp_gas_scales_with_exponent_bytes() {
+        // PUSH2 0x0100 PUSH1 0x02 EXP: a 2-byte exponent
+        let evm = run("61010060020a");
+        let result = evm.result();
+        assert_eq!(result.gas_used, 3 + 3 + 10 + 2 * 50);
+
+        // PUSH0 PUSH1 0x02 EXP: a zero exponent only pays the static cost
+        let evm = run("5f60020a");
+        assert_eq!(evm.result().gas_used, 2 + 3 + 10);
+    }
+
+    #[test]
+    fn bitwise_and_comparison_opcodes() {
+        let minus_one = U256::MAX;
+
+        assert_eq!(apply("12", &[minus_one, U256::zero()]), U256::one());
+        assert_eq!(apply("13", &[minus_one, U256::zero()]), U256::zero());
+        assert_eq!(apply("16", &[0xf0f0.into(), 0xff00.into()]), 0xf000.into());
+        assert_eq!(apply("17", &[0xf0f0.into(), 0xff00.into()]), 0xfff0.into());
+        assert_eq!(apply("18", &[0xf0f0.into(), 0xff00.into()]), 0x0ff0.into());
+        assert_eq!(apply("19", &[U256::zero()]), U256::MAX);
+        assert_eq!(apply("1a", &[31.into(), 0xff.into()]), 0xff.into());
+        assert_eq!(apply("1a", &[30.into(), 0xff.into()]), U256::zero());
+        assert_eq!(apply("1b", &[256.into(), U256::one()]), U256::zero());
+        assert_eq!(apply("1c", &[U256::MAX, U256::MAX]), U256::zero());
+        assert_eq!(apply("1d", &[4.into(), minus_one - 15]), minus_one);
+    }
+
+    #[test]
+    fn selector_extraction() {
+        // PUSH0 CALLDATALOAD PUSH1 0xe0 SHR
+        let evm = run_with_calldata(
+            "5f3560e01c",
+            "a9059cbb0000000000000000000000000000000000000001",
+        );
+        assert_eq!(evm.context.stack.stack, vec![U256::from(0xa9059cbbu64)]);
+    }
+
+    #[test]
+    fn keccak256_of_memory() {
+        // PUSH4 "abc\0" PUSH1 0xe0 SHL PUSH0 MSTORE PUSH1 0x03 PUSH0 KECCAK256
+        let evm = run("636162630060e01b5f5260035f20");
+        let result = evm.result();
+        assert!(result.is_success());
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![word(
+                "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+            )]
+        );
+        // Static costs, 1 word of memory and 1 word hashed
+        assert_eq!(result.gas_used, 3 + 3 + 3 + 2 + 3 + 3 + 3 + 2 + 30 + 6);
+    }
+
+    #[test]
+    fn keccak256_of_empty_range() {
+        // PUSH0 PUSH0 KECCAK256
+        let evm = run("5f5f20");
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![word(
+                "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+            )]
+        );
+        assert_eq!(evm.context.memory.active_words(), 0);
+    }
+
+    #[test]
+    fn sstore_then_sload() {
+        // PUSH1 0x2a PUSH1 0x01 SSTORE PUSH1 0x01 SLOAD
+        let evm = run("602a600155600154");
+        let result = evm.result();
+        assert!(result.is_success());
+        assert_eq!(evm.context.stack.stack, vec![U256::from(0x2a)]);
+        // The SSTORE warms the slot
+        assert_eq!(result.gas_used, 3 + 3 + 22_100 + 3 + 100);
+        assert_eq!(
+            result.storage_changes,
+            vec![StorageChange {
+                address: Address::zero(),
+                key: 1.into(),
+                original: U256::zero(),
+                current: 0x2a.into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn storage_is_per_account() {
+        let address = Address::repeat_byte(0xaa);
+        let mut storage = Storage::new();
+        storage.insert(address, 1.into(), 7.into());
+        storage.insert(Address::zero(), 1.into(), 8.into());
+
+        // PUSH1 0x01 SLOAD
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("600154").unwrap()))
+            .address(address)
+            .storage(storage)
+            .build();
+        let result = evm.run();
+        assert_eq!(evm.context.stack.stack, vec![U256::from(7)]);
+        assert!(result.storage_changes.is_empty());
+    }
+
+    #[test]
+    fn sstore_set_and_clear_is_refunded() {
+        // PUSH1 0x01 PUSH0 SSTORE PUSH0 PUSH0 SSTORE
+        let evm = run("60015f555f5f55");
+        let result = evm.result();
+        assert!(result.is_success());
+        assert!(result.storage_changes.is_empty());
+        // 22100 to set a cold slot and 100 to reset, with the 19900 refund capped at a fifth
+        let used = 3 + 2 + 22_100 + 2 + 2 + 100;
+        assert_eq!(result.gas_refunded, used / 5);
+        assert_eq!(result.gas_used, used - used / 5);
+    }
+
+    #[test]
+    fn sstore_needs_more_than_stipend() {
+        // PUSH1 0x01 PUSH0 SSTORE with 2300 gas left at the SSTORE
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("60015f55").unwrap()))
+            .gas_limit(2_305)
+            .build();
+        let result = evm.run();
+        assert!(matches!(
+            result.status,
+            ExecutionStatus::Halt(ExecutionError::OutOfGas)
+        ));
+        assert!(result.storage_changes.is_empty());
+    }
+
+    #[test]
+    fn transient_storage_is_separate_from_storage() {
+        // PUSH1 0x2a PUSH1 0x01 TSTORE PUSH1 0x01 SLOAD PUSH1 0x01 TLOAD
+        let evm = run("602a60015d60015460015c");
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![U256::zero(), U256::from(0x2a)]
+        );
+        assert!(evm.result().storage_changes.is_empty());
+    }
+
+    #[test]
+    fn revert_returns_data_and_keeps_gas() {
+        // PUSH1 0x2a PUSH0 SSTORE PUSH1 0xaa PUSH0 MSTORE8 PUSH1 0x01 PUSH0 REVERT
+        let mut evm = Evm::builder()
+            .code(Bytes::from(
+                hex::decode("602a5f5560aa5f5360015ffd").unwrap(),
+            ))
+            .gas_limit(100_000)
+            .build();
+        let result = evm.run();
+        assert!(result.is_revert());
+        assert_eq!(result.returndata.as_ref(), &[0xaa]);
+        assert!(result.storage_changes.is_empty());
+        assert_eq!(result.gas_used, 3 + 2 + 22_100 + 3 + 2 + 3 + 3 + 3 + 2);
+        assert_eq!(result.revert_reason(), None);
+    }
+
+    #[test]
+    fn revert_with_panic_reason() {
+        // mstore(0, 0x4e487b71 << 224) mstore(4, 0x01) revert(0, 0x24)
+        let evm = run("634e487b7160e01b5f52600160045260245ffd");
+        let result = evm.result();
+        assert!(result.is_revert());
+        assert_eq!(result.revert_reason(), Some(RevertReason::Panic(1.into())));
+    }
+
+    #[test]
+    fn invalid_consumes_all_gas() {
+        // PUSH1 0x01 INVALID
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("6001fe").unwrap()))
+            .gas_limit(50_000)
+            .build();
+        let result = evm.run();
+        assert!(matches!(
+            result.status,
+            ExecutionStatus::Halt(ExecutionError::Opcode(Errors::InvalidInstruction))
+        ));
+        assert!(result.is_halt());
+        assert_eq!(result.gas_used, 50_000);
+        assert_eq!(result.gas_remaining, 0);
+    }
+
+    #[test]
+    fn log_topics_and_data() {
+        // PUSH1 0xaa PUSH0 MSTORE8 PUSH1 0x02 PUSH1 0x01 PUSH1 0x01 PUSH0 LOG2
+        let evm = run("60aa5f536002600160015fa2");
+        let result = evm.result();
+        assert!(result.is_success());
+        assert_eq!(
+            result.logs,
+            vec![Log {
+                address: Address::zero(),
+                topics: vec![H256::from_low_u64_be(1), H256::from_low_u64_be(2)],
+                data: Bytes::from_static(&[0xaa]),
+            }]
+        );
+        // Static costs, 1 word of memory, 2 topics and 1 byte of data
+        assert_eq!(result.gas_used, 3 + 2 + 3 + 3 + 3 + 3 + 2 + 3 + 375 * 3 + 8);
+    }
+
+    #[test]
+    fn log0_with_empty_data() {
+        // PUSH0 PUSH0 LOG0
+        let evm = run("5f5fa0");
+        let result = evm.result();
+        assert_eq!(result.logs.len(), 1);
+        assert!(result.logs[0].topics.is_empty());
+        assert!(result.logs[0].data.is_empty());
+        assert_eq!(result.gas_used, 2 + 2 + 375);
+    }
+
+    #[test]
+    fn reverted_logs_are_dropped() {
+        // PUSH0 PUSH0 LOG0 PUSH0 PUSH0 REVERT
+        let evm = run("5f5fa05f5ffd");
+        let result = evm.result();
+        assert!(result.is_revert());
+        assert!(result.logs.is_empty());
+    }
+
+    #[test]
+    fn environment_opcodes_read_block_and_tx_env() {
+        let block_env = BlockEnv {
+            number: 17.into(),
+            coinbase: Address::repeat_byte(0xc0),
+            timestamp: 1_700_000_000.into(),
+            gas_limit: 45_000_000.into(),
+            basefee: 7.into(),
+            prevrandao: H256::repeat_byte(0x42),
+            blob_basefee: 3.into(),
+        };
+        let tx_env = TxEnv {
+            caller: Address::repeat_byte(0xee),
+            gas_price: 9.into(),
+            chain_id: 10,
+            ..TxEnv::default()
+        };
+        // ORIGIN CALLER GASPRICE COINBASE TIMESTAMP NUMBER PREVRANDAO GASLIMIT CHAINID BASEFEE
+        // BLOBBASEFEE
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("32333a414243444546484a").unwrap()))
+            .block_env(block_env)
+            .tx_env(tx_env)
+            .build();
+        let result = evm.run();
+        assert!(result.is_success());
+        assert_eq!(result.gas_used, 11 * 2);
+        let origin = U256::from_big_endian(&[0xee; 20]);
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![
+                origin,
+                origin,
+                9.into(),
+                U256::from_big_endian(&[0xc0; 20]),
+                1_700_000_000.into(),
+                17.into(),
+                U256::from_big_endian(&[0x42; 32]),
+                45_000_000.into(),
+                10.into(),
+                7.into(),
+                3.into(),
+            ]
+        );
+    }
+
+    #[test]
+    fn address_and_selfbalance() {
+        let address = Address::from_low_u64_be(0xabcd);
+        let mut db = InMemoryDb::new();
+        db.insert_account(address, Account::new(1_000.into()));
+        // ADDRESS SELFBALANCE
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("3047").unwrap()))
+            .address(address)
+            .database(db)
+            .build();
+        let result = evm.run();
+        assert_eq!(result.gas_used, 2 + 5);
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![U256::from(0xabcd), U256::from(1_000)]
+        );
+    }
+
+    #[test]
+    fn default_environment() {
+        // CHAINID BLOBBASEFEE GASLIMIT
+        let evm = run("464a45");
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![U256::one(), U256::one(), 30_000_000.into()]
+        );
+    }
+
+    fn run_with_db(hex_code: &str, db: InMemoryDb) -> Evm {
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode(hex_code).unwrap()))
+            .database(db)
+            .block_env(BlockEnv {
+                number: 300.into(),
+                ..BlockEnv::default()
+            })
+            .build();
+        evm.run();
+        evm
+    }
+
+    #[test]
+    fn balance_and_extcode_read_the_database() {
+        let mut db = InMemoryDb::new();
+        db.insert_account(
+            Address::from_low_u64_be(0xaa),
+            Account::new(5.into()).with_code(Bytes::from_static(&[0x60, 0x01, 0x00])),
+        );
+        // PUSH1 0xaa BALANCE PUSH1 0xaa EXTCODESIZE PUSH1 0xbb BALANCE PUSH1 0xbb EXTCODESIZE
+        let evm = run_with_db("60aa3160aa3b60bb3160bb3b", db);
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![5.into(), 3.into(), U256::zero(), U256::zero()]
+        );
+        // The first access to each account is cold
+        assert_eq!(evm.result().gas_used, 4 * (3 + 100) + 2 * 2500);
+    }
+
+    #[test]
+    fn extcodecopy_pads_with_zeros() {
+        let mut db = InMemoryDb::new();
+        db.insert_account(
+            Address::from_low_u64_be(0xaa),
+            Account::default().with_code(Bytes::from_static(&[0xde, 0xad])),
+        );
+        // PUSH1 0x04 PUSH1 0x01 PUSH0 PUSH1 0xaa EXTCODECOPY PUSH1 0x20 PUSH0 RETURN
+        let evm = run_with_db("600460015f60aa3c60205ff3", db);
+        let result = evm.result();
+        assert!(result.is_success());
+        assert_eq!(&result.returndata[..4], &[0xad, 0, 0, 0]);
+        // Static costs, a cold account, 1 word of memory and 1 word copied
+        assert_eq!(result.gas_used, 3 + 3 + 2 + 3 + 2600 + 3 + 3 + 3 + 2);
+    }
+
+    #[test]
+    fn extcodehash_of_missing_empty_and_contract_accounts() {
+        let mut db = InMemoryDb::new();
+        db.insert_account(Address::from_low_u64_be(0xaa), Account::new(1.into()));
+        db.insert_account(Address::from_low_u64_be(0xbb), Account::default());
+        // PUSH1 0xaa EXTCODEHASH PUSH1 0xbb EXTCODEHASH PUSH1 0xcc EXTCODEHASH
+        let evm = run_with_db("60aa3f60bb3f60cc3f", db);
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![
+                U256::from_big_endian(crate::account::KECCAK_EMPTY.as_bytes()),
+                U256::zero(),
+                U256::zero()
+            ]
+        );
+    }
+
+    #[test]
+    fn blockhash_only_covers_the_last_256_blocks() {
+        let mut db = InMemoryDb::new();
+        for number in [43, 44, 299, 300] {
+            db.insert_block_hash(number, H256::from_low_u64_be(number));
+        }
+        // Current block is 300.
+        // PUSH1 0x2b BLOCKHASH PUSH1 0x2c BLOCKHASH PUSH2 0x012b BLOCKHASH PUSH2 0x012c BLOCKHASH
+        let evm = run_with_db("602b40602c4061012b4061012c40", db);
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![U256::zero(), 44.into(), 299.into(), U256::zero()]
+        );
+    }
+
+    #[test]
+    fn storage_reads_through_to_the_database() {
+        let mut db = InMemoryDb::new();
+        db.insert_account(
+            Address::zero(),
+            Account::default().with_storage(1.into(), 7.into()),
+        );
+        // PUSH1 0x01 SLOAD PUSH1 0x09 PUSH1 0x01 SSTORE
+        let evm = run_with_db("6001546009600155", db);
+        let result = evm.result();
+        assert_eq!(evm.context.stack.stack, vec![U256::from(7)]);
+        assert_eq!(
+            result.storage_changes,
+            vec![StorageChange {
+                address: Address::zero(),
+                key: 1.into(),
+                original: 7.into(),
+                current: 9.into(),
+            }]
+        );
+        // The SLOAD is cold, then dirtying a non-zero slot costs 2900
+        assert_eq!(result.gas_used, 3 + 2100 + 3 + 3 + 2900);
+    }
+
+    const CALLER_ADDRESS: u64 = 0xc0;
+
+    /// Runs `hex_code` as account 0xc0 holding 100 wei, against `contracts` deployed at the
+    /// given addresses.
+    fn run_calls(hex_code: &str, contracts: &[(u64, &str)]) -> Evm {
+        let mut db = InMemoryDb::new();
+        db.insert_account(
+            Address::from_low_u64_be(CALLER_ADDRESS),
+            Account::new(100.into()),
+        );
+        for (address, code) in contracts {
+            db.insert_account(
+                Address::from_low_u64_be(*address),
+                Account::default().with_code(Bytes::from(hex::decode(code).unwrap())),
+            );
+        }
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode(hex_code).unwrap()))
+            .address(Address::from_low_u64_be(CALLER_ADDRESS))
+            .database(db)
+            .build();
+        evm.run();
+        evm
+    }
+
+    // PUSH1 0x2a PUSH0 MSTORE PUSH1 0x20 PUSH0 RETURN
+    const RETURN_42: &str = "602a5f5260205ff3";
+    // CALLER PUSH0 SSTORE CALLVALUE PUSH1 0x01 SSTORE STOP
+    const STORE_CALLER_AND_VALUE: &str = "335f553460015500";
+
+    #[test]
+    fn call_copies_output_to_memory() {
+        // CALL(0xffff, 0xaa, 0, 0, 0, 0, 0x20) PUSH0 MLOAD RETURNDATASIZE
+        let evm = run_calls("60205f5f5f5f60aa61fffff15f513d", &[(0xaa, RETURN_42)]);
+        assert!(evm.result().is_success());
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![U256::one(), 0x2a.into(), 0x20.into()]
+        );
+        assert_eq!(evm.depth(), 0);
+    }
+
+    #[test]
+    fn call_transfers_value() {
+        // CALL(0xffff, 0xbb, 7, 0, 0, 0, 0) SELFBALANCE PUSH1 0xbb BALANCE
+        let evm = run_calls(
+            "5f5f5f5f600760bb61fffff14760bb31",
+            &[(0xbb, STORE_CALLER_AND_VALUE)],
+        );
+        let result = evm.result();
+        assert!(result.is_success());
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![U256::one(), 93.into(), 7.into()]
+        );
+        let callee = Address::from_low_u64_be(0xbb);
+        assert_eq!(
+            result.storage_changes,
+            vec![
+                StorageChange {
+                    address: callee,
+                    key: U256::zero(),
+                    original: U256::zero(),
+                    current: CALLER_ADDRESS.into(),
+                },
+                StorageChange {
+                    address: callee,
+                    key: U256::one(),
+                    original: U256::zero(),
+                    current: 7.into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reverted_call_rolls_back_state() {
+        // Callee: PUSH1 0x01 PUSH0 SSTORE PUSH1 0x20 PUSH0 REVERT
+        // CALL(0xffff, 0xcc, 5, 0, 0, 0, 0) RETURNDATASIZE SELFBALANCE
+        let evm = run_calls(
+            "5f5f5f5f600560cc61fffff13d47",
+            &[(0xcc, "60015f5560205ffd")],
+        );
+        let result = evm.result();
+        assert!(result.is_success());
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![U256::zero(), 0x20.into(), 100.into()]
+        );
+        assert!(result.storage_changes.is_empty());
+        assert!(evm.state.balance(Address::from_low_u64_be(0xcc)).is_zero());
+    }
+
+    #[test]
+    fn staticcall_forbids_state_changes() {
+        // Callees: PUSH1 0x01 PUSH0 SSTORE STOP, and PUSH0 PUSH0 LOG0 STOP
+        // STATICCALL(0xffff, 0xdd, 0, 0, 0, 0) STATICCALL(0xffff, 0xee, 0, 0, 0, 0)
+        // STATICCALL(0xffff, 0xaa, 0, 0, 0, 0)
+        let evm = run_calls(
+            "5f5f5f5f60dd61fffffa5f5f5f5f60ee61fffffa5f5f5f5f60aa61fffffa",
+            &[(0xdd, "60015f5500"), (0xee, "5f5fa000"), (0xaa, RETURN_42)],
+        );
+        let result = evm.result();
+        assert!(result.is_success());
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![U256::zero(), U256::zero(), U256::one()]
+        );
+        assert!(result.storage_changes.is_empty());
+        assert!(result.logs.is_empty());
+    }
+
+    #[test]
+    fn delegatecall_keeps_caller_context() {
+        // DELEGATECALL(0xffff, 0xbb, 0, 0, 0, 0)
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("5f5f5f5f60bb61fffff4").unwrap()))
+            .address(Address::from_low_u64_be(CALLER_ADDRESS))
+            .caller(Address::from_low_u64_be(0xee))
+            .call_value(3.into())
+            .database({
+                let mut db = InMemoryDb::new();
+                db.insert_account(
+                    Address::from_low_u64_be(0xbb),
+                    Account::default()
+                        .with_code(Bytes::from(hex::decode(STORE_CALLER_AND_VALUE).unwrap())),
+                );
+                db
+            })
+            .build();
+        let result = evm.run();
+        assert_eq!(evm.context.stack.stack, vec![U256::one()]);
+        let caller = Address::from_low_u64_be(CALLER_ADDRESS);
+        assert_eq!(
+            result.storage_changes,
+            vec![
+                StorageChange {
+                    address: caller,
+                    key: U256::zero(),
+                    original: U256::zero(),
+                    current: 0xee.into(),
+                },
+                StorageChange {
+                    address: caller,
+                    key: U256::one(),
+                    original: U256::zero(),
+                    current: 3.into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn callcode_runs_in_caller_storage() {
+        // CALLCODE(0xffff, 0xbb, 0, 0, 0, 0, 0)
+        let evm = run_calls("5f5f5f5f5f60bb61fffff2", &[(0xbb, STORE_CALLER_AND_VALUE)]);
+        let result = evm.result();
+        assert_eq!(evm.context.stack.stack, vec![U256::one()]);
+        assert_eq!(
+            result.storage_changes,
+            vec![StorageChange {
+                address: Address::from_low_u64_be(CALLER_ADDRESS),
+                key: U256::zero(),
+                original: U256::zero(),
+                current: CALLER_ADDRESS.into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn call_without_enough_balance_fails_without_running() {
+        // CALL(0xffff, 0xaa, 101, 0, 0, 0, 0)
+        let evm = run_calls("5f5f5f5f606560aa61fffff1", &[(0xaa, RETURN_42)]);
+        let result = evm.result();
+        assert!(result.is_success());
+        assert_eq!(evm.context.stack.stack, vec![U256::zero()]);
+        assert_eq!(result.steps, 9);
+        // Pushes, a cold CALL and the value surcharge. The unused stipend goes to the caller.
+        assert_eq!(result.gas_used, 17 + 2600 + 9000 - 2300);
+    }
+
+    #[test]
+    fn call_forwards_all_but_one_64th() {
+        let mut db = InMemoryDb::new();
+        // JUMPDEST PUSH0 JUMP
+        db.insert_account(
+            Address::from_low_u64_be(0xff),
+            Account::default().with_code(Bytes::from_static(&[0x5b, 0x5f, 0x56])),
+        );
+        // CALL(~0, 0xff, 0, 0, 0, 0, 0)
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("5f5f5f5f5f60ff5f19f1").unwrap()))
+            .gas_limit(100_000)
+            .database(db)
+            .build();
+        let result = evm.run();
+        assert!(result.is_success());
+        assert_eq!(evm.context.stack.stack, vec![U256::zero()]);
+        // The callee burns what it was given, the caller keeps a 64th of the rest
+        let remaining = 100_000 - 18 - 2600;
+        assert_eq!(result.gas_remaining, remaining / 64);
+    }
+
+    #[test]
+    fn call_depth_is_limited() {
+        // PUSH0 SLOAD PUSH1 0x01 ADD PUSH0 SSTORE CALL(~0, ADDRESS, 0, 0, 0, 0, 0) STOP
+        let code = "5f546001015f555f5f5f5f5f305f19f100";
+        let address = Address::from_low_u64_be(CALLER_ADDRESS);
+        let mut db = InMemoryDb::new();
+        db.insert_account(
+            address,
+            Account::default().with_code(Bytes::from(hex::decode(code).unwrap())),
+        );
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode(code).unwrap()))
+            .address(address)
+            .gas_limit(1_000_000_000_000)
+            .database(db)
+            .build();
+        let result = evm.run();
+        assert!(result.is_success());
+        // The top-level frame plus 1024 nested calls
+        assert_eq!(result.storage_changes[0].current, 1025.into());
+    }
+
+    // Init code: PUSH8 <RETURN_42> PUSH0 MSTORE PUSH1 0x08 PUSH1 0x18 RETURN
+    const DEPLOY_RETURN_42: &str = "67602a5f5260205ff35f5260086018f3";
+
+    #[test]
+    fn create_deploys_returned_code() {
+        // PUSH16 <init code> PUSH0 MSTORE CREATE(0, 0x10, 0x10)
+        // CALL(0xffff, <new contract>, 0, 0, 0, 0, 0x20) PUSH0 MLOAD
+        let code = format!("6f{DEPLOY_RETURN_42}5f52601060105ff060205f5f5f5f8561fffff15f51");
+        let evm = run_calls(&code, &[]);
+        assert!(evm.result().is_success());
+
+        let creator = Address::from_low_u64_be(CALLER_ADDRESS);
+        let created = call::create_address(creator, 0);
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![address_to_word(created), U256::one(), 0x2a.into()]
+        );
+        let state = &evm.state;
+        assert_eq!(state.account(creator).unwrap().nonce, 1);
+        let account = state.account(created).unwrap();
+        assert_eq!(account.nonce, 1);
+        assert_eq!(hex::encode(&account.code), RETURN_42);
+    }
+
+    #[test]
+    fn create2_derives_address_from_salt() {
+        // PUSH16 <init code> PUSH0 MSTORE CREATE2(10, 0x10, 0x10, 0x42)
+        // CREATE2(0, 0x10, 0x10, 0x42)
+        let code = format!("6f{DEPLOY_RETURN_42}5f52604260106010600af56042601060105ff5");
+        let evm = run_calls(&code, &[]);
+        assert!(evm.result().is_success());
+
+        let init_code = hex::decode(DEPLOY_RETURN_42).unwrap();
+        let created = call::create2_address(
+            Address::from_low_u64_be(CALLER_ADDRESS),
+            0x42.into(),
+            &init_code,
+        );
+        // The second creation collides with the first
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![address_to_word(created), U256::zero()]
+        );
+        assert_eq!(evm.state.balance(created), 10.into());
+    }
+
+    #[test]
+    fn reverted_create_keeps_nonce_bump() {
+        // Init code: PUSH1 0x20 PUSH0 REVERT
+        // PUSH4 <init code> PUSH0 MSTORE CREATE(0, 0x1c, 0x04) RETURNDATASIZE
+        let evm = run_calls("6360205ffd5f526004601c5ff03d", &[]);
+        assert!(evm.result().is_success());
+        assert_eq!(evm.context.stack.stack, vec![U256::zero(), 0x20.into()]);
+        let creator = Address::from_low_u64_be(CALLER_ADDRESS);
+        assert_eq!(evm.state.account(creator).unwrap().nonce, 1);
+        assert!(evm
+            .state
+            .account(call::create_address(creator, 0))
+            .is_none());
+    }
+
+    #[test]
+    fn create_rejects_ef_prefixed_code() {
+        // Init code: PUSH1 0xef PUSH0 MSTORE8 PUSH1 0x01 PUSH0 RETURN
+        // PUSH8 <init code> PUSH0 MSTORE CREATE(0, 0x18, 0x08)
+        let mut evm = Evm::builder()
+            .code(Bytes::from(
+                hex::decode("6760ef5f5360015ff35f52600860185ff0").unwrap(),
+            ))
+            .gas_limit(1_000_000)
+            .build();
+        let result = evm.run();
+        assert!(result.is_success());
+        assert_eq!(evm.context.stack.stack, vec![U256::zero()]);
+        // The failed creation burns everything it was given
+        assert!(result.gas_remaining < 1_000_000 / 64);
+    }
+
+    #[test]
+    fn create_enforces_code_size_limit() {
+        // Init code: PUSH2 <size> PUSH0 RETURN
+        // PUSH5 <init code> PUSH0 MSTORE CREATE(0, 0x1b, 0x05), once returning 0x6000 bytes
+        // and once 0x6001
+        let evm = run(concat!(
+            "646160005ff35f526005601b5ff0",
+            "646160015ff35f526005601b5ff0"
+        ));
+        assert!(evm.result().is_success());
+        let stack = &evm.context.stack.stack;
+        assert!(!stack[0].is_zero());
+        assert!(stack[1].is_zero());
+    }
+
+    #[test]
+    fn create_enforces_initcode_size_limit() {
+        // CREATE(0, 0, 0xc001)
+        let evm = run("6200c0015f5ff0");
+        assert!(matches!(
+            evm.result().status,
+            ExecutionStatus::Halt(ExecutionError::Opcode(Errors::InitCodeTooLarge(0xc001)))
+        ));
+    }
+
+    #[test]
+    fn selfdestruct_of_existing_contract_sends_balance() {
+        // PUSH1 0xbb SELFDESTRUCT PUSH1 0x01
+        let evm = run_calls("60bbff6001", &[]);
+        let result = evm.result();
+        assert!(result.is_success());
+        assert!(evm.context.stack.stack.is_empty());
+        // 0xbb is cold and didn't exist, so the transfer creates it
+        assert_eq!(result.gas_used, 3 + 5000 + 2600 + 25000);
+        let state = &evm.state;
+        assert_eq!(state.balance(Address::from_low_u64_be(0xbb)), 100.into());
+        assert!(state
+            .balance(Address::from_low_u64_be(CALLER_ADDRESS))
+            .is_zero());
+        assert!(!state.is_destroyed(Address::from_low_u64_be(CALLER_ADDRESS)));
+    }
+
+    #[test]
+    fn selfdestruct_in_creating_transaction_deletes_contract() {
+        // Init code: PUSH3 <PUSH1 0xbb SELFDESTRUCT> PUSH0 MSTORE PUSH1 0x03 PUSH1 0x1d RETURN
+        // PUSH11 <init code> PUSH0 MSTORE CREATE(10, 0x15, 0x0b)
+        // CALL(0xffff, <new contract>, 0, 0, 0, 0, 0) DUP2 EXTCODESIZE
+        let evm = run_calls(
+            "6a6260bbff5f526003601df35f52600b6015600af05f5f5f5f5f8561fffff1813b",
+            &[],
+        );
+        assert!(evm.result().is_success());
+        let stack = &evm.context.stack.stack;
+        // The code is still there for the rest of the transaction
+        assert_eq!(stack[1..], [U256::one(), 3.into()]);
+
+        let created = word_to_address(stack[0]);
+        let state = &evm.state;
+        assert!(state.code(created).is_empty());
+        assert!(state.balance(created).is_zero());
+        assert_eq!(state.balance(Address::from_low_u64_be(0xbb)), 10.into());
+    }
+
+    #[test]
+    fn selfdestruct_is_forbidden_in_static_context() {
+        // STATICCALL(0xffff, 0xdd, 0, 0, 0, 0)
+        let evm = run_calls("5f5f5f5f60dd61fffffa", &[(0xdd, "60bbff")]);
+        assert!(evm.result().is_success());
+        assert_eq!(evm.context.stack.stack, vec![U256::zero()]);
+    }
+
+    #[test]
+    fn gas_pushes_remaining_gas() {
+        // GAS PUSH1 0x01 GAS
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("5a60015a").unwrap()))
+            .gas_limit(100)
+            .build();
+        evm.run();
+        assert_eq!(
+            evm.context.stack.stack,
+            vec![98.into(), U256::one(), 93.into()]
+        );
+    }
+
+    #[test]
+    fn gas_in_callee_reports_forwarded_gas() {
+        // Callee: GAS PUSH0 MSTORE PUSH1 0x20 PUSH0 RETURN
+        // CALL(1000, 0xaa, 0, 0, 0, 0, 0x20) PUSH0 MLOAD
+        let evm = run_calls("60205f5f5f5f60aa6103e8f15f51", &[(0xaa, "5a5f5260205ff3")]);
+        assert_eq!(evm.context.stack.stack, vec![U256::one(), 998.into()]);
+    }
+
+    #[test]
+    fn gasleft_loop_guard() {
+        // Loop while more than 100 gas is left:
+        // JUMPDEST PUSH1 0x64 GAS GT PUSH1 0x00 JUMPI STOP
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("5b60645a1160005700").unwrap()))
+            .gas_limit(1_000)
+            .build();
+        let result = evm.run();
+        assert!(result.is_success());
+        assert!(result.gas_remaining <= 100);
+    }
+
+    #[test]
+    fn reverting_frame_undoes_its_committed_subcalls() {
+        // 0xbb: CALL(GAS, 0xcc, 0, 0, 0, 0, 0) PUSH0 PUSH0 REVERT
+        // 0xcc: PUSH1 0x02 PUSH0 SSTORE PUSH0 PUSH0 LOG0 STOP
+        // PUSH1 0x01 PUSH0 SSTORE CALL(GAS, 0xbb, 0, 0, 0, 0, 0)
+        let evm = run_calls(
+            "60015f555f5f5f5f5f60bb5af1",
+            &[
+                (0xbb, "5f5f5f5f5f60cc5af15f5ffd"),
+                (0xcc, "60025f555f5fa000"),
+            ],
+        );
+        let result = evm.result();
+        assert!(result.is_success());
+        assert_eq!(evm.context.stack.stack, vec![U256::zero()]);
+        assert_eq!(
+            result.storage_changes,
+            vec![StorageChange {
+                address: Address::from_low_u64_be(CALLER_ADDRESS),
+                key: U256::zero(),
+                original: U256::zero(),
+                current: U256::one(),
+            }]
+        );
+        assert!(result.logs.is_empty());
+    }
+
+    #[test]
+    fn top_level_revert_undoes_transfers() {
+        // CALL(GAS, 0xbb, 7, 0, 0, 0, 0) PUSH0 PUSH0 REVERT
+        let evm = run_calls("5f5f5f5f600760bb5af15f5ffd", &[(0xbb, "00")]);
+        assert!(evm.result().is_revert());
+        let state = &evm.state;
+        assert!(state.balance(Address::from_low_u64_be(0xbb)).is_zero());
+        assert_eq!(
+            state.balance(Address::from_low_u64_be(CALLER_ADDRESS)),
+            100.into()
+        );
+    }
+
+    #[test]
+    fn sload_is_cold_then_warm() {
+        // PUSH0 SLOAD PUSH0 SLOAD
+        let evm = run("5f545f54");
+        assert_eq!(evm.result().gas_used, 2 + 2100 + 2 + 100);
+    }
+
+    #[test]
+    fn precompiles_and_coinbase_start_warm() {
+        // PUSH1 0x01 BALANCE PUSH1 0xcb BALANCE PUSH1 0xbb BALANCE
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("60013160cb3160bb31").unwrap()))
+            .block_env(BlockEnv {
+                coinbase: Address::from_low_u64_be(0xcb),
+                ..Default::default()
+            })
+            .build();
+        let result = evm.run();
+        assert_eq!(result.gas_used, 3 * (3 + 100) + 2500);
+    }
+
+    #[test]
+    fn access_list_prewarms_accounts_and_slots() {
+        // PUSH1 0xaa BALANCE PUSH1 0x01 SLOAD PUSH1 0x02 SLOAD
+        let mut evm = Evm::builder()
+            .code(Bytes::from(hex::decode("60aa3160015460025400").unwrap()))
+            .tx_env(TxEnv {
+                access_list: vec![
+                    AccessListItem {
+                        address: Address::from_low_u64_be(0xaa),
+                        storage_keys: vec![],
+                    },
+                    AccessListItem {
+                        address: Address::zero(),
+                        storage_keys: vec![1.into()],
+                    },
+                ],
+                ..Default::default()
+            })
+            .build();
+        let result = evm.run();
+        // Only slot 2 is cold
+        assert_eq!(result.gas_used, 3 + 100 + 3 + 100 + 3 + 2100);
+    }
+
+    #[test]
+    fn failed_call_cools_what_it_accessed() {
+        // CALL(0xffff, 0xaa, 0, 0, 0, 0, 0) PUSH1 0xbb BALANCE
+        let evm = run_calls(
+            "5f5f5f5f5f60aa61fffff160bb31",
+            // PUSH1 0xbb BALANCE PUSH0 PUSH0 REVERT
+            &[(0xaa, "60bb315f5ffd")],
+        );
+        let result = evm.result();
+        assert!(result.is_success());
+        // 0xaa stays warm since the caller accessed it, 0xbb is cold again
+        let callee = 3 + 2600 + 2 + 2;
+        assert_eq!(result.gas_used, 16 + 2600 + callee + 3 + 2600);
     }
 }
@@ -1,12 +1,13 @@
+use primitive_types::U256;
+
 #[derive(Debug)]
 pub struct Stack {
-    pub stack: Vec<usize>,
+    pub stack: Vec<U256>,
     pub max_depth: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum StackError {
-    InvalidItem(usize),
     StackOverflow,
     StackUnderflow,
     InvalidIndex,
@@ -20,11 +21,7 @@ impl Stack {
         }
     }
 
-    pub fn push(&mut self, item: usize) -> Result<(), StackError> {
-        if item > usize::MAX {
-            return Err(StackError::InvalidItem(item));
-        }
-
+    pub fn push(&mut self, item: U256) -> Result<(), StackError> {
         if self.stack.len() >= self.max_depth {
             return Err(StackError::StackOverflow);
         }
@@ -33,14 +30,14 @@ impl Stack {
         Ok(())
     }
 
-    pub fn pop(&mut self) -> Result<usize, StackError> {
+    pub fn pop(&mut self) -> Result<U256, StackError> {
         match self.stack.pop() {
             Some(item) => Ok(item),
             None => Err(StackError::StackUnderflow),
         }
     }
 
-    pub fn peek(&mut self, index: usize) -> Result<usize, StackError> {
+    pub fn peek(&mut self, index: usize) -> Result<U256, StackError> {
         if self.stack.len() <= index {
             return Err(StackError::StackUnderflow);
         }
@@ -76,22 +73,37 @@ mod tests {
     #[test]
     fn push_and_pop_on_stack() {
         let mut stack = Stack::new(1024);
-        stack.push(42).unwrap();
+        stack.push(42.into()).unwrap();
         assert_eq!(stack.stack.len(), 1);
-        assert_eq!(stack.pop().unwrap(), 42);
+        assert_eq!(stack.pop().unwrap(), 42.into());
         assert_eq!(stack.stack.len(), 0);
     }
 
+    #[test]
+    fn push_and_pop_full_word() {
+        let mut stack = Stack::new(1024);
+        stack.push(U256::MAX).unwrap();
+        assert_eq!(stack.pop().unwrap(), U256::MAX);
+    }
+
+    #[test]
+    fn push_over_max_depth() {
+        let mut stack = Stack::new(2);
+        stack.push(1.into()).unwrap();
+        stack.push(2.into()).unwrap();
+        assert!(stack.push(3.into()).is_err());
+    }
+
     #[test]
     fn test_peek() {
         let mut stack = Stack::new(1024);
-        stack.push(41).unwrap();
-        stack.push(42).unwrap();
-        stack.push(43).unwrap();
+        stack.push(41.into()).unwrap();
+        stack.push(42.into()).unwrap();
+        stack.push(43.into()).unwrap();
 
-        assert_eq!(stack.peek(0).unwrap(), 43);
-        assert_eq!(stack.peek(1).unwrap(), 42);
-        assert_eq!(stack.peek(2).unwrap(), 41);
+        assert_eq!(stack.peek(0).unwrap(), 43.into());
+        assert_eq!(stack.peek(1).unwrap(), 42.into());
+        assert_eq!(stack.peek(2).unwrap(), 41.into());
 
         assert!(stack.peek(3).is_err());
     }
@@ -99,27 +111,27 @@ mod tests {
     #[test]
     fn test_swap() {
         let mut stack = Stack::new(1024);
-        stack.push(41).unwrap();
-        stack.push(42).unwrap();
-        stack.push(43).unwrap();
+        stack.push(41.into()).unwrap();
+        stack.push(42.into()).unwrap();
+        stack.push(43.into()).unwrap();
         // Before swap: [41,42,43] <- Top
-        assert_eq!(stack.peek(0).unwrap(), 43);
-        assert_eq!(stack.peek(1).unwrap(), 42);
-        assert_eq!(stack.peek(2).unwrap(), 41);
+        assert_eq!(stack.peek(0).unwrap(), 43.into());
+        assert_eq!(stack.peek(1).unwrap(), 42.into());
+        assert_eq!(stack.peek(2).unwrap(), 41.into());
 
         stack.swap(1).unwrap();
 
         // After swap: [41,43,42]
-        assert_eq!(stack.peek(0).unwrap(), 42);
-        assert_eq!(stack.peek(1).unwrap(), 43);
-        assert_eq!(stack.peek(2).unwrap(), 41);
+        assert_eq!(stack.peek(0).unwrap(), 42.into());
+        assert_eq!(stack.peek(1).unwrap(), 43.into());
+        assert_eq!(stack.peek(2).unwrap(), 41.into());
 
         stack.swap(2).unwrap();
 
         // After swap 2: [42,43,41]
-        assert_eq!(stack.peek(0).unwrap(), 41);
-        assert_eq!(stack.peek(1).unwrap(), 43);
-        assert_eq!(stack.peek(2).unwrap(), 42);
+        assert_eq!(stack.peek(0).unwrap(), 41.into());
+        assert_eq!(stack.peek(1).unwrap(), 43.into());
+        assert_eq!(stack.peek(2).unwrap(), 42.into());
 
         assert!(stack.swap(3).is_err());
         assert!(stack.swap(0).is_err());
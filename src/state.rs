@@ -0,0 +1,518 @@
+use bytes::Bytes;
+use primitive_types::{H256, U256};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    mem,
+    rc::Rc,
+};
+
+use crate::{
+    account::Account,
+    database::{Database, InMemoryDb},
+    log::Log,
+    storage::{Storage, TransientCheckpoint, TransientStorage},
+    Address,
+};
+
+/// The world state as seen by a transaction: the pre-state in `db` plus everything the
+/// transaction has changed so far. The `Evm` owns it and lends it to the executing frame.
+///
+/// Changes are journaled so that a failing call can be undone: take a `checkpoint` before
+/// the call, then `commit` or `revert_to` it when the call ends. Storage writes must go
+/// through `store_storage` to be journaled.
+#[derive(Debug, Clone)]
+pub struct State {
+    db: Rc<dyn Database>,
+    /// Accounts whose nonce, balance or code changed, copied from `db` on first write.
+    /// Their `storage` is unused; slots live in `storage`.
+    accounts: HashMap<Address, Account>,
+    pub storage: Storage,
+    pub transient_storage: TransientStorage,
+    pub logs: Vec<Log>,
+    /// Accounts created by this transaction.
+    created: HashSet<Address>,
+    /// Accounts that SELFDESTRUCTed, deleted when the transaction ends.
+    destroyed: HashSet<Address>,
+    /// Accounts and slots accessed so far in the transaction, which are warm (EIP-2929).
+    warm_addresses: HashSet<Address>,
+    warm_slots: HashSet<(Address, U256)>,
+    /// How to undo every change since the outermost open checkpoint, oldest first.
+    journal: Vec<JournalEntry>,
+    open_checkpoints: usize,
+}
+
+/// A change to the state, holding what is needed to undo it.
+#[derive(Debug, Clone)]
+enum JournalEntry {
+    /// `accounts` held `previous` for the address before it was written.
+    AccountChanged {
+        address: Address,
+        previous: Option<Account>,
+    },
+    StorageChanged {
+        address: Address,
+        key: U256,
+        previous: U256,
+    },
+    AccountCreated(Address),
+    AccountDestroyed(Address),
+    AddressWarmed(Address),
+    SlotWarmed(Address, U256),
+}
+
+/// A point in the journal that the state can be reverted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Number of checkpoints open once this one is taken, itself included.
+    depth: usize,
+    journal: usize,
+    logs: usize,
+    transient_storage: TransientCheckpoint,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self::new(Rc::new(InMemoryDb::new()))
+    }
+}
+
+impl State {
+    pub fn new(db: Rc<dyn Database>) -> Self {
+        Self {
+            db,
+            accounts: HashMap::new(),
+            storage: Storage::new(),
+            transient_storage: TransientStorage::new(),
+            logs: Vec::new(),
+            created: HashSet::new(),
+            destroyed: HashSet::new(),
+            warm_addresses: HashSet::new(),
+            warm_slots: HashSet::new(),
+            journal: Vec::new(),
+            open_checkpoints: 0,
+        }
+    }
+
+    /// Marks the current state, to later `commit` or `revert_to`. Checkpoints nest and must
+    /// be closed innermost first.
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        self.open_checkpoints += 1;
+        Checkpoint {
+            depth: self.open_checkpoints,
+            journal: self.journal.len(),
+            logs: self.logs.len(),
+            transient_storage: self.transient_storage.checkpoint(),
+        }
+    }
+
+    /// Keeps the changes made since `checkpoint`. They can still be undone by reverting an
+    /// enclosing checkpoint.
+    pub fn commit(&mut self, checkpoint: Checkpoint) {
+        self.close(checkpoint);
+        if self.open_checkpoints == 0 {
+            // Nothing can be reverted anymore
+            self.journal.clear();
+        }
+    }
+
+    /// Marks `checkpoint` as no longer open. Closing anything but the innermost open
+    /// checkpoint would corrupt the journal, so this panics if `checkpoint` was already
+    /// committed or reverted, or if a checkpoint taken after it is still open.
+    fn close(&mut self, checkpoint: Checkpoint) {
+        assert_eq!(
+            checkpoint.depth, self.open_checkpoints,
+            "checkpoint is not the innermost open one"
+        );
+        self.open_checkpoints -= 1;
+    }
+
+    /// Undoes every change made since `checkpoint`.
+    pub fn revert_to(&mut self, checkpoint: Checkpoint) {
+        self.close(checkpoint);
+        while self.journal.len() > checkpoint.journal {
+            match self
+                .journal
+                .pop()
+                .expect("journal is longer than checkpoint")
+            {
+                JournalEntry::AccountChanged { address, previous } => match previous {
+                    Some(account) => {
+                        self.accounts.insert(address, account);
+                    }
+                    None => {
+                        self.accounts.remove(&address);
+                    }
+                },
+                JournalEntry::StorageChanged {
+                    address,
+                    key,
+                    previous,
+                } => self.storage.insert(address, key, previous),
+                JournalEntry::AccountCreated(address) => {
+                    self.created.remove(&address);
+                }
+                JournalEntry::AccountDestroyed(address) => {
+                    self.destroyed.remove(&address);
+                }
+                JournalEntry::AddressWarmed(address) => {
+                    self.warm_addresses.remove(&address);
+                }
+                JournalEntry::SlotWarmed(address, key) => {
+                    self.warm_slots.remove(&(address, key));
+                }
+            }
+        }
+        self.logs.truncate(checkpoint.logs);
+        self.transient_storage
+            .revert_to(checkpoint.transient_storage);
+    }
+
+    pub fn account(&self, address: Address) -> Option<Cow<'_, Account>> {
+        match self.accounts.get(&address) {
+            Some(account) => Some(Cow::Borrowed(account)),
+            None => self.db.account(address).map(Cow::Owned),
+        }
+    }
+
+    /// The account at `address` for writing, created empty if it doesn't exist.
+    pub fn account_mut(&mut self, address: Address) -> &mut Account {
+        self.journal.push(JournalEntry::AccountChanged {
+            address,
+            previous: self.accounts.get(&address).cloned(),
+        });
+        let db = &self.db;
+        self.accounts.entry(address).or_insert_with(|| {
+            db.account(address)
+                .map(|account| Account {
+                    storage: HashMap::new(),
+                    ..account
+                })
+                .unwrap_or_default()
+        })
+    }
+
+    /// Brings the account for a new contract into existence, with nonce 1 (EIP-161).
+    pub fn create_account(&mut self, address: Address) {
+        self.account_mut(address).nonce = 1;
+        if self.created.insert(address) {
+            self.journal.push(JournalEntry::AccountCreated(address));
+        }
+    }
+
+    pub fn balance(&self, address: Address) -> U256 {
+        self.account(address)
+            .map(|account| account.balance)
+            .unwrap_or_default()
+    }
+
+    pub fn code(&self, address: Address) -> Bytes {
+        self.account(address)
+            .map(|account| account.code.clone())
+            .unwrap_or_default()
+    }
+
+    /// Moves `value` wei from `from` to `to`. Returns false, changing nothing, if `from` can't
+    /// afford it.
+    pub fn transfer(&mut self, from: Address, to: Address, value: U256) -> bool {
+        if self.balance(from) < value {
+            return false;
+        }
+        if value.is_zero() {
+            return true;
+        }
+        self.account_mut(from).balance -= value;
+        self.account_mut(to).balance += value;
+        true
+    }
+
+    /// Current value of a slot. Slots are read through from the database on first access and
+    /// kept in `storage` as pre-state from then on.
+    pub fn load_storage(&mut self, address: Address, key: U256) -> U256 {
+        if !self.storage.contains(address, key) {
+            let value = self.db.storage(address, key);
+            self.storage.insert(address, key, value);
+        }
+        self.storage.load(address, key)
+    }
+
+    pub fn store_storage(&mut self, address: Address, key: U256, value: U256) {
+        let previous = self.load_storage(address, key);
+        self.journal.push(JournalEntry::StorageChanged {
+            address,
+            key,
+            previous,
+        });
+        self.storage.store(address, key, value);
+    }
+
+    /// Marks `address` as accessed. Returns true if it was cold, i.e. this is the first access
+    /// in the transaction.
+    pub fn warm_address(&mut self, address: Address) -> bool {
+        let cold = self.warm_addresses.insert(address);
+        if cold {
+            self.journal.push(JournalEntry::AddressWarmed(address));
+        }
+        cold
+    }
+
+    /// Marks a slot as accessed. Returns true if it was cold.
+    pub fn warm_slot(&mut self, address: Address, key: U256) -> bool {
+        let cold = self.warm_slots.insert((address, key));
+        if cold {
+            self.journal.push(JournalEntry::SlotWarmed(address, key));
+        }
+        cold
+    }
+
+    pub fn block_hash(&self, number: u64) -> H256 {
+        self.db.block_hash(number)
+    }
+
+    /// Sends the whole balance of `address` to `beneficiary`. Following EIP-6780, the account
+    /// itself is only destroyed if it was created in this transaction, and only once the
+    /// transaction ends; until then its code keeps running.
+    pub fn selfdestruct(&mut self, address: Address, beneficiary: Address) {
+        let balance = self.balance(address);
+        if !self.created.contains(&address) {
+            self.transfer(address, beneficiary, balance);
+            return;
+        }
+        // Sending the balance to itself burns it
+        self.account_mut(address).balance = U256::zero();
+        if beneficiary != address {
+            self.account_mut(beneficiary).balance += balance;
+        }
+        if self.destroyed.insert(address) {
+            self.journal.push(JournalEntry::AccountDestroyed(address));
+        }
+    }
+
+    pub fn is_destroyed(&self, address: Address) -> bool {
+        self.destroyed.contains(&address)
+    }
+
+    /// Wraps up the transaction: deletes destroyed accounts and drops transient storage.
+    pub fn finish_transaction(&mut self) {
+        for address in mem::take(&mut self.destroyed) {
+            // An empty account is as good as none
+            self.accounts.insert(address, Account::default());
+            self.storage.clear(address);
+        }
+        self.created.clear();
+        self.warm_addresses.clear();
+        self.warm_slots.clear();
+        self.transient_storage.clear();
+        self.journal.clear();
+        self.open_checkpoints = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_with(address: Address, account: Account) -> State {
+        let mut db = InMemoryDb::new();
+        db.insert_account(address, account);
+        State::new(Rc::new(db))
+    }
+
+    #[test]
+    fn transfer_moves_balance() {
+        let from = Address::from_low_u64_be(1);
+        let to = Address::from_low_u64_be(2);
+        let mut state = state_with(from, Account::new(10.into()));
+        assert!(state.transfer(from, to, 4.into()));
+        assert_eq!(state.balance(from), 6.into());
+        assert_eq!(state.balance(to), 4.into());
+    }
+
+    #[test]
+    fn transfer_rejects_insufficient_balance() {
+        let from = Address::from_low_u64_be(1);
+        let to = Address::from_low_u64_be(2);
+        let mut state = state_with(from, Account::new(10.into()));
+        assert!(!state.transfer(from, to, 11.into()));
+        assert_eq!(state.balance(from), 10.into());
+        assert!(state.account(to).is_none());
+    }
+
+    #[test]
+    fn account_mut_keeps_code_and_nonce() {
+        let address = Address::from_low_u64_be(1);
+        let code = Bytes::from_static(&[0x00]);
+        let mut state = state_with(
+            address,
+            Account::new(1.into()).with_nonce(3).with_code(code.clone()),
+        );
+        state.account_mut(address).balance = 2.into();
+        let account = state.account(address).unwrap();
+        assert_eq!(account.nonce, 3);
+        assert_eq!(account.code, code);
+        assert_eq!(account.balance, 2.into());
+    }
+
+    /// Makes up every account on demand, like a database fetching state remotely would.
+    #[derive(Debug)]
+    struct LazyDb;
+
+    impl Database for LazyDb {
+        fn account(&self, address: Address) -> Option<Account> {
+            Some(Account::new(address.to_low_u64_be().into()))
+        }
+
+        fn storage(&self, _address: Address, key: U256) -> U256 {
+            key + 1
+        }
+
+        fn block_hash(&self, _number: u64) -> H256 {
+            H256::zero()
+        }
+    }
+
+    #[test]
+    fn reads_through_to_a_lazy_database() {
+        let address = Address::from_low_u64_be(7);
+        let mut state = State::new(Rc::new(LazyDb));
+        assert_eq!(state.balance(address), 7.into());
+        assert_eq!(state.load_storage(address, 2.into()), 3.into());
+        state.account_mut(address).balance = 1.into();
+        assert_eq!(state.balance(address), 1.into());
+    }
+
+    #[test]
+    fn selfdestruct_of_existing_account_only_moves_balance() {
+        let address = Address::from_low_u64_be(1);
+        let beneficiary = Address::from_low_u64_be(2);
+        let code = Bytes::from_static(&[0x00]);
+        let mut state = state_with(address, Account::new(10.into()).with_code(code.clone()));
+        state.selfdestruct(address, beneficiary);
+        state.finish_transaction();
+        assert_eq!(state.balance(beneficiary), 10.into());
+        assert!(state.balance(address).is_zero());
+        assert_eq!(state.code(address), code);
+    }
+
+    #[test]
+    fn selfdestruct_of_new_account_deletes_it() {
+        let address = Address::from_low_u64_be(1);
+        let mut state = State::default();
+        state.create_account(address);
+        state.account_mut(address).balance = 10.into();
+        state
+            .account_mut(address)
+            .set_code(Bytes::from_static(&[0x00]));
+        state.storage.store(address, U256::one(), 5.into());
+
+        // Sending the balance to itself burns it
+        state.selfdestruct(address, address);
+        assert!(state.is_destroyed(address));
+        assert!(!state.code(address).is_empty());
+
+        state.finish_transaction();
+        assert!(state.account(address).unwrap().is_empty());
+        assert!(state.storage.diff().is_empty());
+    }
+
+    #[test]
+    fn revert_undoes_everything_since_checkpoint() {
+        let from = Address::from_low_u64_be(1);
+        let to = Address::from_low_u64_be(2);
+        let mut state = state_with(from, Account::new(10.into()));
+        state.store_storage(from, U256::one(), 1.into());
+
+        let checkpoint = state.checkpoint();
+        state.transfer(from, to, 4.into());
+        state.store_storage(from, U256::one(), 2.into());
+        state.transient_storage.store(from, U256::one(), 3.into());
+        state.logs.push(Log {
+            address: from,
+            topics: Vec::new(),
+            data: Bytes::new(),
+        });
+        state.create_account(Address::from_low_u64_be(3));
+        state.revert_to(checkpoint);
+
+        assert_eq!(state.balance(from), 10.into());
+        assert!(state.account(to).is_none());
+        assert_eq!(state.load_storage(from, U256::one()), 1.into());
+        assert!(state.transient_storage.is_empty());
+        assert!(state.logs.is_empty());
+        assert!(state.account(Address::from_low_u64_be(3)).is_none());
+    }
+
+    #[test]
+    fn committed_changes_revert_with_enclosing_checkpoint() {
+        let address = Address::from_low_u64_be(1);
+        let mut state = State::default();
+
+        let outer = state.checkpoint();
+        state.store_storage(address, U256::one(), 1.into());
+        let inner = state.checkpoint();
+        state.store_storage(address, U256::one(), 2.into());
+        state.commit(inner);
+        assert_eq!(state.load_storage(address, U256::one()), 2.into());
+
+        state.revert_to(outer);
+        assert!(state.load_storage(address, U256::one()).is_zero());
+        assert!(state.storage.diff().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "checkpoint is not the innermost open one")]
+    fn closing_a_checkpoint_twice_panics() {
+        let mut state = State::default();
+        let checkpoint = state.checkpoint();
+        state.commit(checkpoint);
+        state.commit(checkpoint);
+    }
+
+    #[test]
+    #[should_panic(expected = "checkpoint is not the innermost open one")]
+    fn committing_a_nested_checkpoint_twice_panics() {
+        let mut state = State::default();
+        let _outer = state.checkpoint();
+        let inner = state.checkpoint();
+        state.commit(inner);
+        state.commit(inner);
+    }
+
+    #[test]
+    #[should_panic(expected = "checkpoint is not the innermost open one")]
+    fn reverting_a_nested_checkpoint_twice_panics() {
+        let mut state = State::default();
+        let _outer = state.checkpoint();
+        let inner = state.checkpoint();
+        state.revert_to(inner);
+        state.revert_to(inner);
+    }
+
+    #[test]
+    #[should_panic(expected = "checkpoint is not the innermost open one")]
+    fn closing_an_outer_checkpoint_first_panics() {
+        let mut state = State::default();
+        let outer = state.checkpoint();
+        let _inner = state.checkpoint();
+        state.commit(outer);
+    }
+
+    #[test]
+    fn inner_revert_keeps_outer_changes() {
+        let address = Address::from_low_u64_be(1);
+        let mut state = State::default();
+
+        let outer = state.checkpoint();
+        state.create_account(address);
+        state.store_storage(address, U256::one(), 1.into());
+        let inner = state.checkpoint();
+        state.selfdestruct(address, address);
+        state.store_storage(address, U256::one(), 2.into());
+        state.revert_to(inner);
+        state.commit(outer);
+
+        assert_eq!(state.load_storage(address, U256::one()), 1.into());
+        assert_eq!(state.account(address).unwrap().nonce, 1);
+        assert!(!state.is_destroyed(address));
+    }
+}
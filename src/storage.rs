@@ -0,0 +1,237 @@
+use primitive_types::U256;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::Address;
+
+/// Persistent contract storage, one slot map per account.
+#[derive(Debug, Clone, Default)]
+pub struct Storage {
+    slots: HashMap<Address, HashMap<U256, U256>>,
+    /// Value of every written slot before its first write, used for the diff and SSTORE gas.
+    original: HashMap<(Address, U256), U256>,
+}
+
+/// A slot whose value differs from the one it had before the run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageChange {
+    pub address: Address,
+    pub key: U256,
+    pub original: U256,
+    pub current: U256,
+}
+
+impl Storage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets a slot without recording it as a change, e.g. to seed the pre-state.
+    pub fn insert(&mut self, address: Address, key: U256, value: U256) {
+        self.slots.entry(address).or_default().insert(key, value);
+    }
+
+    /// Whether the slot has been inserted or written, as opposed to never touched.
+    pub fn contains(&self, address: Address, key: U256) -> bool {
+        self.slots
+            .get(&address)
+            .is_some_and(|slots| slots.contains_key(&key))
+    }
+
+    pub fn load(&self, address: Address, key: U256) -> U256 {
+        self.slots
+            .get(&address)
+            .and_then(|slots| slots.get(&key))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn store(&mut self, address: Address, key: U256, value: U256) {
+        let current = self.load(address, key);
+        self.original.entry((address, key)).or_insert(current);
+        self.insert(address, key, value);
+    }
+
+    /// Zeroes every slot of `address` this storage knows of, as when its account is deleted.
+    pub fn clear(&mut self, address: Address) {
+        let keys: Vec<U256> = self
+            .slots
+            .get(&address)
+            .map(|slots| slots.keys().copied().collect())
+            .unwrap_or_default();
+        for key in keys {
+            self.store(address, key, U256::zero());
+        }
+    }
+
+    /// Value the slot held before it was first written during this run.
+    pub fn original(&self, address: Address, key: U256) -> U256 {
+        match self.original.get(&(address, key)) {
+            Some(value) => *value,
+            None => self.load(address, key),
+        }
+    }
+
+    /// Every slot whose value changed, ordered by address and key.
+    pub fn diff(&self) -> Vec<StorageChange> {
+        let changes: BTreeMap<_, _> = self
+            .original
+            .iter()
+            .filter_map(|(&(address, key), &original)| {
+                let current = self.load(address, key);
+                (current != original).then_some(((address, key), (original, current)))
+            })
+            .collect();
+        changes
+            .into_iter()
+            .map(|((address, key), (original, current))| StorageChange {
+                address,
+                key,
+                original,
+                current,
+            })
+            .collect()
+    }
+}
+
+/// Transient storage (EIP-1153): per-transaction slots that are discarded once the
+/// transaction ends. Writes are journaled so a reverting call frame can undo its own.
+#[derive(Debug, Clone, Default)]
+pub struct TransientStorage {
+    slots: HashMap<(Address, U256), U256>,
+    /// Previous value of every write, in order.
+    journal: Vec<((Address, U256), U256)>,
+}
+
+/// Position in the transient storage journal to roll back to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransientCheckpoint(usize);
+
+impl TransientStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load(&self, address: Address, key: U256) -> U256 {
+        self.slots.get(&(address, key)).copied().unwrap_or_default()
+    }
+
+    pub fn store(&mut self, address: Address, key: U256, value: U256) {
+        let previous = self.load(address, key);
+        self.journal.push(((address, key), previous));
+        self.slots.insert((address, key), value);
+    }
+
+    pub fn checkpoint(&self) -> TransientCheckpoint {
+        TransientCheckpoint(self.journal.len())
+    }
+
+    /// Undoes every write made since `checkpoint`.
+    pub fn revert_to(&mut self, checkpoint: TransientCheckpoint) {
+        while self.journal.len() > checkpoint.0 {
+            let (slot, previous) = self
+                .journal
+                .pop()
+                .expect("journal is longer than checkpoint");
+            self.slots.insert(slot, previous);
+        }
+    }
+
+    /// Discards everything, as happens at the end of a transaction.
+    pub fn clear(&mut self) {
+        self.slots.clear();
+        self.journal.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.values().all(|value| value.is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unset_slot_is_zero() {
+        let storage = Storage::new();
+        assert_eq!(storage.load(Address::zero(), 1.into()), U256::zero());
+    }
+
+    #[test]
+    fn slots_are_keyed_per_account() {
+        let mut storage = Storage::new();
+        storage.store(Address::zero(), 1.into(), 10.into());
+        storage.store(Address::repeat_byte(1), 1.into(), 20.into());
+
+        assert_eq!(storage.load(Address::zero(), 1.into()), 10.into());
+        assert_eq!(storage.load(Address::repeat_byte(1), 1.into()), 20.into());
+    }
+
+    #[test]
+    fn original_survives_repeated_writes() {
+        let mut storage = Storage::new();
+        storage.insert(Address::zero(), 1.into(), 5.into());
+        storage.store(Address::zero(), 1.into(), 6.into());
+        storage.store(Address::zero(), 1.into(), 7.into());
+
+        assert_eq!(storage.original(Address::zero(), 1.into()), 5.into());
+        assert_eq!(storage.original(Address::zero(), 2.into()), U256::zero());
+    }
+
+    #[test]
+    fn diff_reports_only_changed_slots() {
+        let mut storage = Storage::new();
+        storage.insert(Address::zero(), 1.into(), 5.into());
+        storage.store(Address::zero(), 1.into(), 6.into());
+        storage.store(Address::zero(), 3.into(), 9.into());
+        // Written and restored, so no change
+        storage.store(Address::zero(), 2.into(), 1.into());
+        storage.store(Address::zero(), 2.into(), U256::zero());
+
+        assert_eq!(
+            storage.diff(),
+            vec![
+                StorageChange {
+                    address: Address::zero(),
+                    key: 1.into(),
+                    original: 5.into(),
+                    current: 6.into(),
+                },
+                StorageChange {
+                    address: Address::zero(),
+                    key: 3.into(),
+                    original: U256::zero(),
+                    current: 9.into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn transient_store_and_load() {
+        let mut transient = TransientStorage::new();
+        transient.store(Address::zero(), 1.into(), 2.into());
+        assert_eq!(transient.load(Address::zero(), 1.into()), 2.into());
+        assert_eq!(
+            transient.load(Address::repeat_byte(1), 1.into()),
+            U256::zero()
+        );
+
+        transient.clear();
+        assert!(transient.is_empty());
+        assert_eq!(transient.load(Address::zero(), 1.into()), U256::zero());
+    }
+
+    #[test]
+    fn transient_revert_to_checkpoint() {
+        let mut transient = TransientStorage::new();
+        transient.store(Address::zero(), 1.into(), 1.into());
+        let checkpoint = transient.checkpoint();
+        transient.store(Address::zero(), 1.into(), 2.into());
+        transient.store(Address::zero(), 2.into(), 3.into());
+
+        transient.revert_to(checkpoint);
+        assert_eq!(transient.load(Address::zero(), 1.into()), 1.into());
+        assert_eq!(transient.load(Address::zero(), 2.into()), U256::zero());
+    }
+}